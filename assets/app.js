@@ -1,164 +1,268 @@
-const loginSection = document.getElementById("login-section");
-const twoFASection = document.getElementById("2fa-section");
-const signupSection = document.getElementById("signup-section");
+const RECONNECT_MS = 2000;
 
-const signupLink = document.getElementById("signup-link");
-const twoFALoginLink = document.getElementById("2fa-login-link");
-const signupLoginLink = document.getElementById("signup-login-link");
+const tokenForm = document.getElementById("token-form");
+const errAlert = document.getElementById("err-alert");
+const updated = document.getElementById("updated");
+const tbody = document.getElementById("endpoints");
+const windowPicker = document.getElementById("window-picker");
 
-signupLink.addEventListener("click", (e) => {
-    e.preventDefault();
+let selectedWindow = "1m";
+// the last snapshot with events applied, by pool and URI
+const endpoints = new Map();
+let feed = null;
 
-    loginSection.style.display = "none";
-    twoFASection.style.display = "none";
-    signupSection.style.display = "block";
-});
+tokenForm.token.value = sessionStorage.getItem("token") || "";
 
-twoFALoginLink.addEventListener("click", (e) => {
+tokenForm.addEventListener("submit", (e) => {
     e.preventDefault();
 
-    loginSection.style.display = "block";
-    twoFASection.style.display = "none";
-    signupSection.style.display = "none";
+    sessionStorage.setItem("token", tokenForm.token.value.trim());
+    connect();
 });
 
-signupLoginLink.addEventListener("click", (e) => {
-    e.preventDefault();
+windowPicker.addEventListener("click", (e) => {
+    const window = e.target.dataset.window;
+    if (window === undefined) {
+        return;
+    }
 
-    loginSection.style.display = "block";
-    twoFASection.style.display = "none";
-    signupSection.style.display = "none";
+    selectedWindow = window;
+    for (const button of windowPicker.children) {
+        button.classList.toggle("active", button.dataset.window === window);
+    }
+    render();
 });
 
 // -----------------------------------------------------
 
-const loginForm = document.getElementById("login-form");
-const loginButton = document.getElementById("login-form-submit");
-const loginErrAlter = document.getElementById("login-err-alert");
+// without a token the browser's jwt cookie is used instead
+function headers() {
+    const token = sessionStorage.getItem("token");
+    const headers = { "Content-Type": "application/json" };
+    if (token) {
+        headers["Authorization"] = `Bearer ${token}`;
+    }
+    return headers;
+}
 
-loginButton.addEventListener("click", (e) => {
-    e.preventDefault();
+function showError(message) {
+    if (message) {
+        errAlert.textContent = `Error: ${message}`;
+        errAlert.style.display = "block";
+    } else {
+        errAlert.style.display = "none";
+    }
+}
 
-    const email = loginForm.email.value;
-    const password = loginForm.password.value;
-
-    fetch('/login', {
-        method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
-        body: JSON.stringify({ email, password }),
-    }).then(response => {
-        if (response.status === 206) {
-            TwoFAForm.email.value = email;
-            response.json().then(data => {
-                TwoFAForm.login_attempt_id.value = data.loginAttemptId;
-            });
-
-            loginForm.email.value = "";
-            loginForm.password.value = "";
-
-            loginSection.style.display = "none";
-            twoFASection.style.display = "block";
-            signupSection.style.display = "none";
-            loginErrAlter.style.display = "none";
-        } else if (response.status === 200) {
-            loginForm.email.value = "";
-            loginForm.password.value = "";
-            loginErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
-        } else {
-            response.json().then(data => {
-                let error_msg = data.error;
-                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
-                    loginErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
-                    loginErrAlter.style.display = "block";
-                } else {
-                    loginErrAlter.style.display = "none";
-                }
-            });
-        }
-    });
-});
+async function errorMessage(response) {
+    try {
+        const data = await response.json();
+        return data.error || response.statusText;
+    } catch {
+        return response.statusText;
+    }
+}
 
-const signupForm = document.getElementById("signup-form");
-const signupButton = document.getElementById("signup-form-submit");
-const signupErrAlter = document.getElementById("signup-err-alert");
+function key(pool, uri) {
+    return `${pool} ${uri}`;
+}
 
-signupButton.addEventListener("click", (e) => {
-    e.preventDefault();
+// container names and memory limits only come with the snapshot, so new endpoints reload it
+async function loadSnapshot(signal) {
+    const response = await fetch("/printstats", { headers: headers(), credentials: "same-origin", signal });
+    if (!response.ok) {
+        throw new Error(await errorMessage(response));
+    }
 
-    const email = signupForm.email.value;
-    const password = signupForm.password.value;
-    const requires2FA = signupForm.twoFA.checked;
-
-    fetch('/signup', {
-        method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
-        body: JSON.stringify({ email, password, requires2FA }),
-    }).then(response => {
-        if (response.ok) {
-            signupForm.email.value = "";
-            signupForm.password.value = "";
-            signupForm.twoFA.checked = false;
-            signupErrAlter.style.display = "none";
-            alert("You have successfully created a user.");
-            loginSection.style.display = "block";
-            twoFASection.style.display = "none";
-            signupSection.style.display = "none";
-        } else {
-            response.json().then(data => {
-                let error_msg = data.error;
-                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
-                    signupErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
-                    signupErrAlter.style.display = "block";
-                } else {
-                    signupErrAlter.style.display = "none";
+    endpoints.clear();
+    for (const endpoint of await response.json()) {
+        endpoints.set(key(endpoint.pool, endpoint.uri), endpoint);
+    }
+    render();
+}
+
+function apply(event) {
+    const endpoint = endpoints.get(key(event.pool, event.endpoint));
+    switch (event.type) {
+        case "endpoint_added":
+            return endpoint === undefined;
+        case "endpoint_removed":
+            endpoints.delete(key(event.pool, event.endpoint));
+            break;
+        case "endpoint_state":
+            if (endpoint === undefined) {
+                return true;
+            }
+            endpoint.active_server = event.active;
+            endpoint.draining = event.draining;
+            endpoint.weight = event.weight;
+            break;
+        case "health":
+            if (endpoint !== undefined) {
+                endpoint.active_server = event.healthy;
+            }
+            break;
+        case "stats":
+            for (const delta of event.endpoints) {
+                const stats = endpoints.get(key(delta.pool, delta.endpoint));
+                if (stats === undefined) {
+                    continue;
                 }
-            });
+                stats.count_concurrent_connections = delta.in_flight;
+                stats.windows = delta.windows;
+                stats.cpu_percentage = delta.cpu_percentage;
+                stats.memory_percentage = delta.memory_percentage;
+            }
+            break;
+    }
+    render();
+    return false;
+}
+
+// EventSource cannot send the token, so the feed is read as a streamed fetch
+async function subscribe(signal) {
+    const response = await fetch("/admin/events", { headers: headers(), credentials: "same-origin", signal });
+    if (!response.ok) {
+        throw new Error(await errorMessage(response));
+    }
+
+    const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+    let buffered = "";
+    for (;;) {
+        const { value, done } = await reader.read();
+        if (done) {
+            throw new Error("event feed closed");
         }
-    });
-});
 
-const TwoFAForm = document.getElementById("2fa-form");
-const TwoFAButton = document.getElementById("2fa-form-submit");
-const TwoFAErrAlter = document.getElementById("2fa-err-alert");
+        buffered += value;
+        const messages = buffered.split("\n\n");
+        buffered = messages.pop();
+        let reload = false;
+        for (const message of messages) {
+            const data = message
+                .split("\n")
+                .filter((line) => line.startsWith("data:"))
+                .map((line) => line.slice(5).trim())
+                .join("\n");
+            if (data) {
+                reload = apply(JSON.parse(data)) || reload;
+            }
+        }
+        if (reload) {
+            await loadSnapshot(signal);
+        }
+        showError(null);
+        updated.textContent = `Updated ${new Date().toLocaleTimeString()}`;
+    }
+}
 
-TwoFAButton.addEventListener("click", (e) => {
-    e.preventDefault();
+async function connect() {
+    if (feed) {
+        feed.abort();
+    }
+    const current = new AbortController();
+    feed = current;
 
-    const email = TwoFAForm.email.value;
-    const loginAttemptId = TwoFAForm.login_attempt_id.value;
-    const TwoFACode = TwoFAForm.email_code.value;
-
-    fetch('/verify-2fa', {
-        method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
-        body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode }),
-    }).then(response => {
-        if (response.ok) {
-            TwoFAForm.email.value = "";
-            TwoFAForm.email_code.value = "";
-            TwoFAForm.login_attempt_id.value = "";
-            TwoFAErrAlter.style.display = "none";
-            alert("You have successfully logged in.");
-            loginSection.style.display = "block";
-            twoFASection.style.display = "none";
-            signupSection.style.display = "none";
-        } else {
-            response.json().then(data => {
-                let error_msg = data.error;
-                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
-                    TwoFAErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
-                    TwoFAErrAlter.style.display = "block";
-                } else {
-                    TwoFAErrAlter.style.display = "none";
-                }
-            });
+    while (!current.signal.aborted) {
+        try {
+            await loadSnapshot(current.signal);
+            await subscribe(current.signal);
+        } catch (err) {
+            if (current.signal.aborted) {
+                return;
+            }
+            showError(err.message);
         }
+        await new Promise((resolve) => setTimeout(resolve, RECONNECT_MS));
+    }
+}
+
+async function action(name, endpoint) {
+    const response = await fetch(`/admin/endpoints/${name}`, {
+        method: "POST",
+        headers: headers(),
+        credentials: "same-origin",
+        body: JSON.stringify({ uri: endpoint.uri, pool: endpoint.pool }),
     });
-});
\ No newline at end of file
+    if (!response.ok) {
+        showError(await errorMessage(response));
+    }
+}
+
+// -----------------------------------------------------
+
+function cell(text, className) {
+    const td = document.createElement("td");
+    td.textContent = text;
+    if (className) {
+        td.className = className;
+    }
+    return td;
+}
+
+function number(value, digits, suffix) {
+    return value === null || value === undefined ? "–" : `${value.toFixed(digits)}${suffix}`;
+}
+
+function state(endpoint) {
+    const badge = document.createElement("span");
+    if (endpoint.draining) {
+        badge.className = "badge bg-warning text-dark";
+        badge.textContent = "draining";
+    } else if (endpoint.active_server) {
+        badge.className = "badge bg-success";
+        badge.textContent = "active";
+    } else {
+        badge.className = "badge bg-secondary";
+        badge.textContent = "inactive";
+    }
+    const td = document.createElement("td");
+    td.appendChild(badge);
+    return td;
+}
+
+function buttons(endpoint) {
+    const td = document.createElement("td");
+    td.className = "text-end text-nowrap";
+    for (const [name, style] of [["enable", "success"], ["disable", "secondary"], ["drain", "warning"]]) {
+        const button = document.createElement("button");
+        button.type = "button";
+        button.className = `btn btn-sm btn-outline-${style} ms-1`;
+        button.textContent = name;
+        button.addEventListener("click", () => action(name, endpoint));
+        td.appendChild(button);
+    }
+    return td;
+}
+
+function render() {
+    const rows = [...endpoints.values()]
+        .sort((a, b) => a.pool.localeCompare(b.pool) || a.uri.localeCompare(b.uri))
+        .map((endpoint) => {
+            const window = endpoint.windows.find((w) => w.window === selectedWindow) || {};
+            const memory = endpoint.memory_percentage === undefined
+                ? "–"
+                : `${number(endpoint.memory_percentage, 1, "%")} of ${(endpoint.memory_limit / 1048576).toFixed(0)} MiB`;
+
+            const tr = document.createElement("tr");
+            tr.append(
+                cell(endpoint.pool),
+                cell(endpoint.container_name ? `${endpoint.uri} (${endpoint.container_name})` : endpoint.uri),
+                state(endpoint),
+                cell(endpoint.weight, "text-end"),
+                cell(endpoint.count_concurrent_connections, "text-end"),
+                cell(number(window.request_rate, 2, ""), "text-end"),
+                cell(number(window.error_rate * 100, 1, "%"), "text-end"),
+                cell(number(window.p50_ms, 1, " ms"), "text-end"),
+                cell(number(window.p90_ms, 1, " ms"), "text-end"),
+                cell(number(window.p99_ms, 1, " ms"), "text-end"),
+                cell(number(endpoint.cpu_percentage, 1, "%"), "text-end"),
+                cell(memory, "text-end"),
+                buttons(endpoint),
+            );
+            return tr;
+        });
+    tbody.replaceChildren(...rows);
+}
+
+connect();
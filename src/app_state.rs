@@ -1,17 +1,82 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::EndpointStore;
+use crate::{
+    domain::{DockerSnapshot, EndpointStore},
+    services::{
+        access_log::AccessLogger,
+        error_pages::ErrorPages,
+        events::EventBus,
+        metrics::Metrics,
+        pools::{Pools, DEFAULT_POOL},
+        routing::RouteTable,
+    },
+    utils::auth::AuthSettings,
+};
 
 pub type EndpointStoreType = Arc<RwLock<dyn EndpointStore + Send + Sync>>;
+pub type DockerStatsCache = Arc<RwLock<DockerSnapshot>>;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub endpoint_store: EndpointStoreType,
+    pub pools: Arc<Pools>,
+    /// Which pool each request goes to.
+    pub routes: Arc<RouteTable>,
+    pub auth: Arc<AuthSettings>,
+    /// Latest container samples, kept up to date by the stats collector.
+    pub docker_stats: DockerStatsCache,
+    pub metrics: Arc<Metrics>,
+    pub access_log: Arc<AccessLogger>,
+    pub events: EventBus,
+    /// Pages for requests no backend answers, and the maintenance switch.
+    pub error_pages: Arc<ErrorPages>,
 }
 
 impl AppState {
-    pub fn new(endpoint_store: EndpointStoreType) -> Self {
-        Self { endpoint_store }
+    pub fn new(endpoint_store: EndpointStoreType, auth: AuthSettings) -> Self {
+        Self {
+            pools: Arc::new(Pools::single(endpoint_store)),
+            routes: Arc::new(RouteTable::new(Vec::new(), DEFAULT_POOL)),
+            auth: Arc::new(auth),
+            docker_stats: DockerStatsCache::default(),
+            metrics: Arc::new(Metrics::new().expect("Failed to register metrics")),
+            access_log: Arc::new(AccessLogger::disabled()),
+            events: EventBus::default(),
+            error_pages: Arc::new(ErrorPages::default()),
+        }
+    }
+
+    /// Replace the single default pool with `pools`, routed by `routes`.
+    pub fn with_routes(self, pools: Pools, routes: RouteTable) -> Self {
+        Self {
+            pools: Arc::new(pools),
+            routes: Arc::new(routes),
+            ..self
+        }
+    }
+
+    pub fn with_docker_stats(self, docker_stats: DockerStatsCache) -> Self {
+        Self {
+            docker_stats,
+            ..self
+        }
+    }
+
+    pub fn with_access_log(self, access_log: AccessLogger) -> Self {
+        Self {
+            access_log: Arc::new(access_log),
+            ..self
+        }
+    }
+
+    pub fn with_events(self, events: EventBus) -> Self {
+        Self { events, ..self }
+    }
+
+    pub fn with_error_pages(self, error_pages: ErrorPages) -> Self {
+        Self {
+            error_pages: Arc::new(error_pages),
+            ..self
+        }
     }
 }
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use axum::http::Uri;
+use bollard::secret::ContainerSummary;
+
+/// Explicit endpoint URIs (comma separated) served by a container, for setups the
+/// port and network heuristics cannot see, such as a remote Docker daemon.
+pub const ENDPOINT_LABEL: &str = "roundest-robin.endpoint";
+
+/// Normalised `(host, port)` a container can be reached at.
+type Address = (String, u16);
+
+const LOOPBACK_HOSTS: [&str; 4] = ["localhost", "127.0.0.1", "::1", "host.docker.internal"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerRef {
+    pub id: String,
+    pub name: String,
+}
+
+impl ContainerRef {
+    pub fn from_summary(container: &ContainerSummary) -> Option<Self> {
+        let id = container.id.clone()?;
+        // Docker gives long IDs; name is often easier to read:
+        let name = container
+            .names
+            .as_ref()
+            .and_then(|names| names.first())
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| id.clone());
+        Some(Self { id, name })
+    }
+}
+
+/// Maps endpoint addresses to the containers serving them.
+///
+/// A container is reachable at, in order of precedence:
+///
+/// 1. any URI listed in its `roundest-robin.endpoint` label
+/// 2. its published host ports; ports bound to all interfaces match loopback hosts
+/// 3. its network IPs on each exposed container port
+#[derive(Debug, Default)]
+pub struct ContainerIndex {
+    by_address: HashMap<Address, ContainerRef>,
+}
+
+impl ContainerIndex {
+    pub fn new(containers: &[ContainerSummary]) -> Self {
+        let mut by_address = HashMap::new();
+
+        // lowest precedence first, so later inserts win
+        let sources: [fn(&ContainerSummary) -> Vec<Address>; 3] =
+            [network_addresses, published_addresses, label_addresses];
+
+        for addresses in sources {
+            for container in containers {
+                let Some(container_ref) = ContainerRef::from_summary(container) else {
+                    continue;
+                };
+                for address in addresses(container) {
+                    by_address.insert(address, container_ref.clone());
+                }
+            }
+        }
+
+        Self { by_address }
+    }
+
+    pub fn find(&self, uri: &Uri) -> Option<&ContainerRef> {
+        self.by_address.get(&address_key(uri)?)
+    }
+}
+
+fn normalize_host(host: &str) -> String {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_ascii_lowercase();
+    if LOOPBACK_HOSTS.contains(&host.as_str()) {
+        "localhost".to_string()
+    } else {
+        host
+    }
+}
+
+fn address_key(uri: &Uri) -> Option<Address> {
+    let host = normalize_host(uri.host()?);
+    let port = uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("https") => Some(443),
+        Some("http") => Some(80),
+        _ => None,
+    })?;
+    Some((host, port))
+}
+
+fn label_addresses(container: &ContainerSummary) -> Vec<Address> {
+    container
+        .labels
+        .as_ref()
+        .and_then(|labels| labels.get(ENDPOINT_LABEL))
+        .map(|uris| {
+            uris.split(',')
+                .filter_map(|uri| uri.trim().parse::<Uri>().ok())
+                .filter_map(|uri| address_key(&uri))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn published_addresses(container: &ContainerSummary) -> Vec<Address> {
+    container
+        .ports
+        .iter()
+        .flatten()
+        .filter_map(|port| {
+            let public_port = port.public_port?;
+            let host = match port.ip.as_deref() {
+                None | Some("") | Some("0.0.0.0") | Some("::") => "localhost",
+                Some(ip) => ip,
+            };
+            Some((normalize_host(host), public_port))
+        })
+        .collect()
+}
+
+fn network_addresses(container: &ContainerSummary) -> Vec<Address> {
+    let ips: Vec<&str> = container
+        .network_settings
+        .iter()
+        .filter_map(|settings| settings.networks.as_ref())
+        .flat_map(|networks| networks.values())
+        .filter_map(|network| network.ip_address.as_deref())
+        .filter(|ip| !ip.is_empty())
+        .collect();
+
+    ips.iter()
+        .flat_map(|ip| {
+            container
+                .ports
+                .iter()
+                .flatten()
+                .map(move |port| (normalize_host(ip), port.private_port))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::secret::{ContainerSummaryNetworkSettings, EndpointSettings, Port};
+
+    use super::*;
+
+    fn container(id: &str, ports: Vec<Port>) -> ContainerSummary {
+        ContainerSummary {
+            id: Some(id.to_string()),
+            names: Some(vec![format!("/{}", id)]),
+            ports: Some(ports),
+            ..Default::default()
+        }
+    }
+
+    fn port(private_port: u16, public_port: Option<u16>, ip: Option<&str>) -> Port {
+        Port {
+            ip: ip.map(str::to_string),
+            private_port,
+            public_port,
+            typ: None,
+        }
+    }
+
+    #[test]
+    fn test_find_by_published_port() {
+        let index = ContainerIndex::new(&[
+            container("web-1", vec![port(8000, Some(7001), Some("0.0.0.0"))]),
+            container("web-2", vec![port(8000, Some(7002), Some("127.0.0.1"))]),
+        ]);
+
+        let found = index
+            .find(&Uri::from_static("http://localhost:7001/"))
+            .unwrap();
+        assert_eq!(found.id, "web-1");
+        assert_eq!(found.name, "web-1");
+        assert_eq!(
+            index
+                .find(&Uri::from_static("http://127.0.0.1:7002"))
+                .unwrap()
+                .id,
+            "web-2"
+        );
+        assert!(index
+            .find(&Uri::from_static("http://localhost:7003"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_by_network_ip_and_label() {
+        let mut networked = container("api", vec![port(8080, None, None)]);
+        networked.network_settings = Some(ContainerSummaryNetworkSettings {
+            networks: Some(HashMap::from([(
+                "backend".to_string(),
+                EndpointSettings {
+                    ip_address: Some("172.18.0.3".to_string()),
+                    ..Default::default()
+                },
+            )])),
+        });
+
+        let mut labelled = container("remote", vec![]);
+        labelled.labels = Some(HashMap::from([(
+            ENDPOINT_LABEL.to_string(),
+            "http://docker-host.internal:9000, https://api.example.com".to_string(),
+        )]));
+
+        let index = ContainerIndex::new(&[networked, labelled]);
+
+        assert_eq!(
+            index
+                .find(&Uri::from_static("http://172.18.0.3:8080"))
+                .unwrap()
+                .id,
+            "api"
+        );
+        assert_eq!(
+            index
+                .find(&Uri::from_static("http://docker-host.internal:9000/"))
+                .unwrap()
+                .id,
+            "remote"
+        );
+        assert_eq!(
+            index
+                .find(&Uri::from_static("https://api.example.com"))
+                .unwrap()
+                .id,
+            "remote"
+        );
+    }
+}
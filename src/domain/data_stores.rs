@@ -1,16 +1,35 @@
+use axum::http::Uri;
+
 use super::Endpoint;
 
 #[async_trait::async_trait]
 pub trait EndpointStore {
     async fn add_endpoint(&mut self, endpoint: Endpoint) -> Result<(), EndpointStoreError>;
-    async fn get_next_endpoint(&self) -> Result<Endpoint, EndpointStoreError>;
+    async fn remove_endpoint(&mut self, uri: &Uri) -> Result<Endpoint, EndpointStoreError>;
+    async fn get_next_endpoint(&self) -> Result<Endpoint, EndpointStoreError> {
+        self.get_next_endpoint_excluding(&[]).await
+    }
+    /// Like `get_next_endpoint`, but never picks one of `excluded`, such as the endpoints a
+    /// request has already failed on.
+    async fn get_next_endpoint_excluding(
+        &self,
+        excluded: &[Uri],
+    ) -> Result<Endpoint, EndpointStoreError>;
     async fn get_all_endpoints(&self) -> Result<Vec<Endpoint>, EndpointStoreError>;
-    async fn check_for_dead_servers(&self) -> ();
+    async fn check_for_dead_servers(&self) -> Vec<HealthCheckResult>;
+}
+
+/// Outcome of checking one active endpoint; unhealthy endpoints have just been deactivated.
+#[derive(Debug, Clone)]
+pub struct HealthCheckResult {
+    pub endpoint: Endpoint,
+    pub healthy: bool,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum EndpointStoreError {
     EndpointAlreadyExists,
+    EndpointNotFound,
     NoEndpoints,
     InvalidCredentials,
     UnexpectedError,
@@ -1,134 +1,71 @@
-use bollard::container::{ListContainersOptions, StatsOptions};
-use bollard::secret::ContainerSummary;
-use bollard::Docker;
-use bytesize::ByteSize;
-use futures_util::StreamExt;
+use axum::http::Uri;
+use bollard::container::{MemoryStatsStats, Stats};
 use std::collections::HashMap;
+use std::time::Instant;
 
+use super::{ContainerIndex, ContainerRef};
+
+#[derive(Debug, Clone)]
 pub struct DockerStats {
+    pub container_id: String,
+    pub container_name: String,
     pub cpu_percentage: f64,
+    /// Memory in use excluding reclaimable page cache, as `docker stats` reports it.
     pub memory_usage: u64,
     pub memory_limit: u64,
     pub memory_percentage: f64,
     pub network_rx_bytes: u64,
     pub network_tx_bytes: u64,
+    pub block_read_bytes: u64,
+    pub block_write_bytes: u64,
+    pub pids: u64,
+    pub sampled_at: Instant,
 }
 
-pub async fn get_docker_stats() -> Result<HashMap<String, DockerStats>, Box<dyn std::error::Error>>
-{
-    let docker = Docker::connect_with_unix(
-        "/Users/johnsimon/.docker/run/docker.sock",
-        120, // timeout seconds
-        bollard::API_DEFAULT_VERSION,
-    )?;
-
-    // list all running containers
-    let running = docker
-        .list_containers(Some(ListContainersOptions::<String> {
-            all: false, // false = running only
-            ..Default::default()
-        }))
-        .await?;
-
-    let mut container_stats: HashMap<String, DockerStats> = HashMap::new();
-    for container in running.iter() {
-        let result = print_stats_per_container(container, &docker).await?;
-        let port = container
-            .image
-            .as_ref()
-            .map(|img| img.split('-').last().unwrap_or(""))
-            .unwrap_or("");
-        let url = format!("http://localhost:{}/", port);
-        container_stats.insert(url, result);
-    }
+/// Latest stats for every running container, and the index used to match them to endpoints.
+#[derive(Default)]
+pub struct DockerSnapshot {
+    pub index: ContainerIndex,
+    pub stats: HashMap<String, DockerStats>,
+}
 
-    Ok(container_stats)
+impl DockerSnapshot {
+    pub fn for_endpoint(&self, uri: &Uri) -> Option<&DockerStats> {
+        let container = self.index.find(uri)?;
+        self.stats.get(&container.id)
+    }
 }
 
-async fn print_stats_per_container(
-    first: &ContainerSummary,
-    docker: &Docker,
-) -> Result<DockerStats, Box<dyn std::error::Error>> {
-    let binding = first.id.clone().unwrap();
-    let container_id = first.id.as_ref().unwrap_or(&binding);
-    // Docker gives long IDs; name is often easier to read:
-    let display_name = first
-        .names
-        .as_ref()
-        .and_then(|names| names.get(0))
-        .map(|s| s.trim_start_matches('/'))
-        .unwrap_or(container_id);
-
-    println!(
-        "Inspecting stats for container: {} ({})",
-        display_name, container_id
-    );
-
-    //
-    // 3. Request stats.
-    //
-    // docker.stats() returns an async stream of Stat objects.
-    // stream=true means continuous stream. We'll just grab the first item and stop.
-    //
-    let mut stats_stream = docker.stats(
-        container_id,
-        Some(StatsOptions {
-            stream: false,
-            one_shot: true, // some dockerds support this flag, bollard may expose it as `one_shot`
-        }),
-    );
-
-    // Pull exactly one stats frame
-    let stats_frame = stats_stream
-        .next()
-        .await
-        .ok_or("docker returned no stats")??;
-
-    //
-    // 4. Extract interesting fields.
-    //
-    // The docker API gives cpu_stats and precpu_stats so you can compute deltas.
-    // We'll calculate CPU % the same way docker CLI does:
-    //
-    // CPU% = (cpu_delta / system_delta) * #cpus * 100.0
-    //
-    let cpu_pct = calc_cpu_percent(&stats_frame);
-
-    // Memory usage vs limit
-    let mem_usage = stats_frame.memory_stats.usage.unwrap_or_default() as u64;
-    let mem_limit = stats_frame.memory_stats.limit.unwrap_or_default() as u64;
-
-    // Network rx/tx (can be multiple interfaces; sum them)
-    let (rx_bytes, tx_bytes) = calc_network_io(&stats_frame);
-
-    //
-    // 5. Print it.
-    //
-    // println!("\n=== Live stats ===");
-    // println!("Name:            {}", display_name);
-    // println!("CPU usage:       {:.2}%", cpu_pct);
-    // println!(
-    //     "Memory usage:    {} / {} ({:.2}%)",
-    //     ByteSize(mem_usage),
-    //     ByteSize(mem_limit),
-    //     pct(mem_usage, mem_limit),
-    // );
-    // println!(
-    //     "Network I/O:     ↓ {} / ↑ {}",
-    //     ByteSize(rx_bytes),
-    //     ByteSize(tx_bytes),
-    // );
-
-    let stats = DockerStats {
-        cpu_percentage: cpu_pct,
-        memory_usage: mem_usage,
-        memory_limit: mem_limit,
-        memory_percentage: pct(mem_usage, mem_limit),
-        network_rx_bytes: rx_bytes,
-        network_tx_bytes: tx_bytes,
-    };
-    // blkio, pids, etc. are also available on stats_frame if you need them.
-    Ok(stats)
+impl DockerStats {
+    /// Extract the interesting fields from one frame of the Docker stats stream.
+    ///
+    /// The docker API gives cpu_stats and precpu_stats so you can compute deltas.
+    pub fn from_frame(container: &ContainerRef, stats_frame: &Stats) -> Self {
+        let cpu_pct = calc_cpu_percent(stats_frame);
+
+        // Memory usage vs limit
+        let mem_usage = calc_memory_usage(stats_frame);
+        let mem_limit = stats_frame.memory_stats.limit.unwrap_or_default();
+
+        // Network rx/tx (can be multiple interfaces; sum them)
+        let (rx_bytes, tx_bytes) = calc_network_io(stats_frame);
+        let (read_bytes, write_bytes) = calc_block_io(stats_frame);
+
+        DockerStats {
+            container_id: container.id.clone(),
+            container_name: container.name.clone(),
+            cpu_percentage: cpu_pct,
+            memory_usage: mem_usage,
+            memory_limit: mem_limit,
+            memory_percentage: pct(mem_usage, mem_limit),
+            network_rx_bytes: rx_bytes,
+            network_tx_bytes: tx_bytes,
+            block_read_bytes: read_bytes,
+            block_write_bytes: write_bytes,
+            pids: stats_frame.pids_stats.current.unwrap_or_default(),
+            sampled_at: Instant::now(),
+        }
+    }
 }
 
 /// Calculate the CPU percentage in the Docker CLI style.
@@ -137,22 +74,21 @@ async fn print_stats_per_container(
 ///   cpu_delta = cpu_stats.cpu_usage.total_usage - precpu_stats.cpu_usage.total_usage
 ///   system_delta = cpu_stats.system_cpu_usage - precpu_stats.system_cpu_usage
 ///   cpu_percent = (cpu_delta / system_delta) * cpu_stats.online_cpus * 100.0
-fn calc_cpu_percent(stats: &bollard::container::Stats) -> f64 {
+fn calc_cpu_percent(stats: &Stats) -> f64 {
     let cpu_stats = &stats.cpu_stats;
     let precpu_stats = &stats.precpu_stats;
 
     let cpu_total = cpu_stats.cpu_usage.total_usage as f64;
-    let pre_cpu_total = precpu_stats
-        .cpu_usage
-        .percpu_usage
-        .as_ref()
-        .map(|v| v.iter().sum::<u64>() as f64)
-        .unwrap_or(0.0);
+    let pre_cpu_total = precpu_stats.cpu_usage.total_usage as f64;
 
     let cpu_delta = cpu_total - pre_cpu_total;
 
+    // the first frame of a stream has no previous sample to diff against
+    let pre_system_cpu = match precpu_stats.system_cpu_usage {
+        Some(pre_system_cpu) if pre_system_cpu > 0 => pre_system_cpu as f64,
+        _ => return 0.0,
+    };
     let system_cpu = cpu_stats.system_cpu_usage.unwrap_or(0) as f64;
-    let pre_system_cpu = precpu_stats.system_cpu_usage.unwrap_or(0) as f64;
 
     let system_delta = system_cpu - pre_system_cpu;
 
@@ -172,12 +108,43 @@ fn calc_cpu_percent(stats: &bollard::container::Stats) -> f64 {
     }
 }
 
+/// Usage minus inactive page cache, matching the docker CLI for cgroup v1 and v2.
+fn calc_memory_usage(stats: &Stats) -> u64 {
+    let usage = stats.memory_stats.usage.unwrap_or_default();
+    let cache = match &stats.memory_stats.stats {
+        Some(MemoryStatsStats::V1(v1)) => v1.total_inactive_file,
+        Some(MemoryStatsStats::V2(v2)) => v2.inactive_file,
+        None => 0,
+    };
+    usage.saturating_sub(cache)
+}
+
+/// Sum bytes read and written across all block devices.
+fn calc_block_io(stats: &Stats) -> (u64, u64) {
+    let mut read = 0u64;
+    let mut write = 0u64;
+    for entry in stats
+        .blkio_stats
+        .io_service_bytes_recursive
+        .iter()
+        .flatten()
+    {
+        // cgroup v1 reports "Read"/"Write", v2 "read"/"write"
+        if entry.op.eq_ignore_ascii_case("read") {
+            read += entry.value;
+        } else if entry.op.eq_ignore_ascii_case("write") {
+            write += entry.value;
+        }
+    }
+    (read, write)
+}
+
 /// Sum RX/TX bytes across all reported network interfaces.
-fn calc_network_io(stats: &bollard::container::Stats) -> (u64, u64) {
+fn calc_network_io(stats: &Stats) -> (u64, u64) {
     if let Some(networks) = &stats.networks {
         let mut rx = 0u64;
         let mut tx = 0u64;
-        for (_ifname, data) in networks {
+        for data in networks.values() {
             rx += data.rx_bytes;
             tx += data.tx_bytes;
         }
@@ -194,3 +161,102 @@ fn pct(used: u64, total: u64) -> f64 {
         (used as f64 / total as f64) * 100.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cgroup v2 frame as returned by `GET /containers/{id}/stats`, trimmed to what bollard needs.
+    const FRAME: &str = r#"{
+        "read": "2024-05-01T10:00:01.000000000Z",
+        "preread": "2024-05-01T10:00:00.000000000Z",
+        "num_procs": 0,
+        "pids_stats": { "current": 12, "limit": 4096 },
+        "networks": {
+            "eth0": { "rx_bytes": 1000, "tx_bytes": 400, "rx_packets": 0, "tx_packets": 0,
+                      "rx_errors": 0, "tx_errors": 0, "rx_dropped": 0, "tx_dropped": 0 },
+            "eth1": { "rx_bytes": 24, "tx_bytes": 100, "rx_packets": 0, "tx_packets": 0,
+                      "rx_errors": 0, "tx_errors": 0, "rx_dropped": 0, "tx_dropped": 0 }
+        },
+        "memory_stats": {
+            "usage": 300000000,
+            "limit": 1000000000,
+            "stats": {
+                "anon": 0, "file": 0, "kernel_stack": 0, "slab": 0, "sock": 0, "shmem": 0,
+                "file_mapped": 0, "file_dirty": 0, "file_writeback": 0, "anon_thp": 0,
+                "inactive_anon": 0, "active_anon": 0, "inactive_file": 100000000,
+                "active_file": 0, "unevictable": 0, "slab_reclaimable": 0,
+                "slab_unreclaimable": 0, "pgfault": 0, "pgmajfault": 0,
+                "workingset_refault": 0, "workingset_activate": 0,
+                "workingset_nodereclaim": 0, "pgrefill": 0, "pgscan": 0, "pgsteal": 0,
+                "pgactivate": 0, "pgdeactivate": 0, "pglazyfree": 0, "pglazyfreed": 0,
+                "thp_fault_alloc": 0, "thp_collapse_alloc": 0
+            }
+        },
+        "blkio_stats": {
+            "io_service_bytes_recursive": [
+                { "major": 8, "minor": 0, "op": "read", "value": 4096 },
+                { "major": 8, "minor": 0, "op": "write", "value": 8192 },
+                { "major": 8, "minor": 16, "op": "Read", "value": 1024 }
+            ]
+        },
+        "cpu_stats": {
+            "cpu_usage": { "total_usage": 3000000000, "usage_in_usermode": 0, "usage_in_kernelmode": 0 },
+            "system_cpu_usage": 120000000000,
+            "online_cpus": 4,
+            "throttling_data": { "periods": 0, "throttled_periods": 0, "throttled_time": 0 }
+        },
+        "precpu_stats": {
+            "cpu_usage": { "total_usage": 2000000000, "usage_in_usermode": 0, "usage_in_kernelmode": 0 },
+            "system_cpu_usage": 100000000000,
+            "online_cpus": 4,
+            "throttling_data": { "periods": 0, "throttled_periods": 0, "throttled_time": 0 }
+        },
+        "storage_stats": {},
+        "name": "/web-1",
+        "id": "abc123"
+    }"#;
+
+    fn container() -> ContainerRef {
+        ContainerRef {
+            id: "abc123".to_string(),
+            name: "web-1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_from_frame() {
+        let frame: Stats = serde_json::from_str(FRAME).unwrap();
+        let stats = DockerStats::from_frame(&container(), &frame);
+
+        // 1s of CPU over 20s of system time on 4 CPUs
+        assert!((stats.cpu_percentage - 20.0).abs() < 1e-9);
+        // inactive file cache is not counted as used
+        assert_eq!(stats.memory_usage, 200_000_000);
+        assert_eq!(stats.memory_limit, 1_000_000_000);
+        assert!((stats.memory_percentage - 20.0).abs() < 1e-9);
+        assert_eq!(
+            (stats.network_rx_bytes, stats.network_tx_bytes),
+            (1024, 500)
+        );
+        assert_eq!(
+            (stats.block_read_bytes, stats.block_write_bytes),
+            (5120, 8192)
+        );
+        assert_eq!(stats.pids, 12);
+    }
+
+    #[test]
+    fn test_first_frame_has_no_cpu_delta() {
+        let mut frame: Stats = serde_json::from_str(FRAME).unwrap();
+        // the first frame of a stream carries an empty previous sample
+        frame.precpu_stats.cpu_usage.total_usage = 0;
+        frame.precpu_stats.system_cpu_usage = None;
+        frame.memory_stats.stats = None;
+
+        let stats = DockerStats::from_frame(&container(), &frame);
+
+        assert_eq!(stats.cpu_percentage, 0.0);
+        assert_eq!(stats.memory_usage, 300_000_000);
+    }
+}
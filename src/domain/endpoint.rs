@@ -1,10 +1,13 @@
 use std::{
     sync::atomic::{AtomicBool, AtomicUsize, Ordering},
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use axum::http::Uri;
 
+use super::{RequestWindows, WindowStats};
+
 #[derive(Clone, Debug)]
 pub struct Endpoint {
     pub uri: Uri,
@@ -12,6 +15,18 @@ pub struct Endpoint {
     pub count_failure: Arc<AtomicUsize>,
     pub count_concurrent_connections: Arc<AtomicUsize>,
     pub active_server: Arc<AtomicBool>,
+    pub weight: Arc<AtomicUsize>,
+    pub draining: Arc<AtomicBool>,
+    pub resources: Arc<Mutex<Option<ResourceSample>>>,
+    pub windows: Arc<Mutex<RequestWindows>>,
+}
+
+/// Latest CPU and memory usage of the container behind an endpoint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResourceSample {
+    pub cpu_percentage: f64,
+    pub memory_percentage: f64,
+    pub sampled_at: Instant,
 }
 
 impl Endpoint {
@@ -22,8 +37,27 @@ impl Endpoint {
             count_failure: Arc::new(AtomicUsize::new(0)),
             count_concurrent_connections: Arc::new(AtomicUsize::new(0)),
             active_server: Arc::new(AtomicBool::new(true)),
+            weight: Arc::new(AtomicUsize::new(1)),
+            draining: Arc::new(AtomicBool::new(false)),
+            resources: Arc::new(Mutex::new(None)),
+            windows: Arc::new(Mutex::new(RequestWindows::default())),
+        }
+    }
+
+    /// Parse an absolute endpoint URI such as `http://localhost:7001`.
+    pub fn parse_uri(uri: &str) -> Option<Uri> {
+        let uri: Uri = uri.trim().parse().ok()?;
+        if uri.scheme().is_none() || uri.authority().is_none() {
+            return None;
         }
+        Some(uri)
+    }
+
+    pub fn with_weight(self, weight: usize) -> Self {
+        self.set_weight(weight);
+        self
     }
+
     pub fn incr_success(&self) {
         self.count_success.fetch_add(1, Ordering::Relaxed);
     }
@@ -51,6 +85,32 @@ impl Endpoint {
             .fetch_sub(1, Ordering::SeqCst);
     }
 
+    pub fn weight(&self) -> usize {
+        self.weight.load(Ordering::Relaxed)
+    }
+
+    /// Relative share of traffic; clamped to at least 1 so every endpoint stays selectable.
+    pub fn set_weight(&self, weight: usize) {
+        self.weight.store(weight.max(1), Ordering::Relaxed);
+    }
+
+    pub fn concurrent_connection_count(&self) -> usize {
+        self.count_concurrent_connections.load(Ordering::SeqCst)
+    }
+
+    /// Stop sending new requests here while in-flight ones finish.
+    pub fn drain(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+    }
+
+    pub fn cancel_drain(&self) {
+        self.draining.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
     pub fn activate(&self) {
         self.active_server.store(true, Ordering::Relaxed);
     }
@@ -58,4 +118,24 @@ impl Endpoint {
     pub fn deactivate(&self) {
         self.active_server.store(false, Ordering::Relaxed);
     }
+
+    pub fn record_resources(&self, sample: ResourceSample) {
+        *self.resources.lock().unwrap() = Some(sample);
+    }
+
+    pub fn resources(&self) -> Option<ResourceSample> {
+        *self.resources.lock().unwrap()
+    }
+
+    /// Add a finished request to the rolling windows; `error` means no response or a 5xx.
+    pub fn record_request(&self, latency: Duration, error: bool) {
+        self.windows
+            .lock()
+            .unwrap()
+            .record(Instant::now(), latency, error);
+    }
+
+    pub fn request_windows(&self) -> Vec<WindowStats> {
+        self.windows.lock().unwrap().snapshot(Instant::now())
+    }
 }
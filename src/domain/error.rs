@@ -1,9 +1,24 @@
+#[derive(Debug)]
 pub enum RouterError {
     UserAlreadyExists,
     InvalidCredentials,
     IncorrectCredentials,
     MissingToken,
     InvalidToken,
+    InsufficientScope,
+    EndpointAlreadyExists,
+    EndpointNotFound,
+    PoolNotFound,
+    RouteNotFound,
+    InvalidSplit,
+    UnknownHost,
+    MisdirectedRequest,
+    InvalidEndpoint,
+    PayloadTooLarge,
+    NoEndpointAvailable,
+    BadGateway,
+    GatewayTimeout,
+    Maintenance,
     UnexpectedError,
 }
 
@@ -1,9 +1,13 @@
+pub mod container_index;
 pub mod data_stores;
 pub mod dockerstats;
 pub mod endpoint;
 pub mod error;
+pub mod request_windows;
 
+pub use container_index::*;
 pub use data_stores::*;
 pub use dockerstats::*;
 pub use endpoint::*;
 pub use error::*;
+pub use request_windows::*;
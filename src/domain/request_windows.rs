@@ -0,0 +1,213 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use hdrhistogram::Histogram;
+use serde::Serialize;
+
+/// Windows reported for every endpoint, shortest first.
+pub const WINDOWS: [(&str, Duration); 3] = [
+    ("1m", Duration::from_secs(60)),
+    ("5m", Duration::from_secs(5 * 60)),
+    ("15m", Duration::from_secs(15 * 60)),
+];
+
+/// Each window is kept as this many buckets, so old requests age out in steps of a
+/// tenth of the window.
+const BUCKETS_PER_WINDOW: u32 = 10;
+
+/// Latencies above this are recorded as this.
+const MAX_LATENCY_MICROS: u64 = 60_000_000;
+
+/// Request counts and latency distribution over the last 1, 5 and 15 minutes.
+#[derive(Debug)]
+pub struct RequestWindows {
+    windows: Vec<RollingWindow>,
+}
+
+/// Summary of one window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowStats {
+    pub window: &'static str,
+    pub requests: u64,
+    pub errors: u64,
+    /// Requests per second.
+    pub request_rate: f64,
+    /// Share of requests that failed, from 0 to 1.
+    pub error_rate: f64,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+}
+
+impl Default for RequestWindows {
+    fn default() -> Self {
+        Self {
+            windows: WINDOWS
+                .iter()
+                .map(|(name, span)| RollingWindow::new(name, *span))
+                .collect(),
+        }
+    }
+}
+
+impl RequestWindows {
+    /// Record a request that finished at `now`; `error` means no response or a 5xx.
+    pub fn record(&mut self, now: Instant, latency: Duration, error: bool) {
+        for window in &mut self.windows {
+            window.record(now, latency, error);
+        }
+    }
+
+    pub fn snapshot(&self, now: Instant) -> Vec<WindowStats> {
+        self.windows
+            .iter()
+            .map(|window| window.snapshot(now))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct RollingWindow {
+    name: &'static str,
+    span: Duration,
+    bucket_width: Duration,
+    buckets: VecDeque<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    start: Instant,
+    requests: u64,
+    errors: u64,
+    latency: Histogram<u32>,
+}
+
+impl RollingWindow {
+    fn new(name: &'static str, span: Duration) -> Self {
+        Self {
+            name,
+            span,
+            bucket_width: span / BUCKETS_PER_WINDOW,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, now: Instant, latency: Duration, error: bool) {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| now.saturating_duration_since(bucket.start) >= self.span)
+        {
+            self.buckets.pop_front();
+        }
+
+        let current = self
+            .buckets
+            .back()
+            .is_some_and(|bucket| now.saturating_duration_since(bucket.start) < self.bucket_width);
+        if !current {
+            self.buckets.push_back(Bucket {
+                start: now,
+                requests: 0,
+                errors: 0,
+                latency: new_histogram(),
+            });
+        }
+
+        let bucket = self.buckets.back_mut().expect("bucket was just pushed");
+        bucket.requests += 1;
+        if error {
+            bucket.errors += 1;
+        }
+        bucket
+            .latency
+            .saturating_record((latency.as_micros() as u64).clamp(1, MAX_LATENCY_MICROS));
+    }
+
+    fn snapshot(&self, now: Instant) -> WindowStats {
+        let mut requests = 0;
+        let mut errors = 0;
+        let mut latency = new_histogram();
+        for bucket in &self.buckets {
+            if now.saturating_duration_since(bucket.start) >= self.span {
+                continue;
+            }
+            requests += bucket.requests;
+            errors += bucket.errors;
+            // both histograms share the same bounds, so this cannot fail
+            let _ = latency.add(&bucket.latency);
+        }
+
+        let percentile = |quantile: f64| {
+            (requests > 0).then(|| latency.value_at_quantile(quantile) as f64 / 1000.0)
+        };
+        WindowStats {
+            window: self.name,
+            requests,
+            errors,
+            request_rate: requests as f64 / self.span.as_secs_f64(),
+            error_rate: if requests > 0 {
+                errors as f64 / requests as f64
+            } else {
+                0.0
+            },
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+        }
+    }
+}
+
+fn new_histogram() -> Histogram<u32> {
+    Histogram::new_with_bounds(1, MAX_LATENCY_MICROS, 2).expect("histogram bounds are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_and_rates() {
+        let start = Instant::now();
+        let mut windows = RequestWindows::default();
+        for ms in 1..=100 {
+            windows.record(start, Duration::from_millis(ms), ms > 95);
+        }
+
+        let stats = windows.snapshot(start);
+        let one_minute = &stats[0];
+        assert_eq!(one_minute.window, "1m");
+        assert_eq!(one_minute.requests, 100);
+        assert_eq!(one_minute.errors, 5);
+        assert!((one_minute.request_rate - 100.0 / 60.0).abs() < 1e-9);
+        assert!((one_minute.error_rate - 0.05).abs() < 1e-9);
+        // two significant digits
+        assert!((one_minute.p50_ms.unwrap() - 50.0).abs() <= 0.5);
+        assert!((one_minute.p90_ms.unwrap() - 90.0).abs() <= 1.0);
+        assert!((one_minute.p99_ms.unwrap() - 99.0).abs() <= 1.0);
+        assert!((stats[2].request_rate - 100.0 / 900.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_old_requests_age_out() {
+        let start = Instant::now();
+        let mut windows = RequestWindows::default();
+        windows.record(start, Duration::from_millis(500), true);
+        windows.record(
+            start + Duration::from_secs(90),
+            Duration::from_millis(10),
+            false,
+        );
+
+        let stats = windows.snapshot(start + Duration::from_secs(90));
+        assert_eq!((stats[0].requests, stats[0].errors), (1, 0));
+        assert!(stats[0].p99_ms.unwrap() < 11.0);
+        assert_eq!((stats[1].requests, stats[1].errors), (2, 1));
+
+        let idle = windows.snapshot(start + Duration::from_secs(20 * 60));
+        assert!(idle.iter().all(|window| window.requests == 0));
+        assert_eq!(idle[0].p50_ms, None);
+    }
+}
@@ -1,10 +1,12 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 
 use app_state::AppState;
 use axum::{
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
     http::{Method, StatusCode},
+    middleware::AddExtension,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{get, post},
     serve::Serve,
     Json, Router,
 };
@@ -13,7 +15,11 @@ use routes::routeme;
 use serde::{Deserialize, Serialize};
 use tower_http::cors::CorsLayer;
 
-use crate::routes::print_stats;
+use crate::routes::{
+    add_endpoint, disable_endpoint, drain_endpoint, enable_endpoint, get_dashboard,
+    get_dashboard_css, get_dashboard_js, get_events, get_maintenance, get_metrics, get_splits,
+    print_stats, set_maintenance, set_split,
+};
 
 pub mod app_state;
 pub mod domain;
@@ -21,13 +27,27 @@ pub mod routes;
 pub mod services;
 pub mod utils;
 
+/// Server that hands each request the client's address, for access logs.
+type Server = Serve<
+    IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    AddExtension<Router, ConnectInfo<SocketAddr>>,
+>;
+
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Server,
+    admin_server: Option<Server>,
     pub address: String,
+    pub admin_address: Option<String>,
 }
 
 impl Application {
-    pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
+    /// Build the proxy listener on `address`. Admin and stats routes are served from
+    /// `admin_address` when given, otherwise from the same listener as proxied traffic.
+    pub async fn build(
+        app_state: AppState,
+        address: &str,
+        admin_address: Option<&str>,
+    ) -> Result<Self, Box<dyn Error>> {
         let allowed_origins = [
             "http://localhost:8000".parse()?,
             "http://[YOUR_DROPLET_IP]:8000".parse()?,
@@ -38,22 +58,75 @@ impl Application {
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
-        let router = Router::new()
+        let admin_router = Router::new()
+            .route("/dashboard", get(get_dashboard))
+            .route("/dashboard/app.js", get(get_dashboard_js))
+            .route("/dashboard/dashboard.css", get(get_dashboard_css))
             .route("/printstats", get(print_stats))
+            .route("/metrics", get(get_metrics))
+            .route("/admin/events", get(get_events))
+            .route("/admin/endpoints", post(add_endpoint))
+            .route("/admin/endpoints/enable", post(enable_endpoint))
+            .route("/admin/endpoints/disable", post(disable_endpoint))
+            .route("/admin/endpoints/drain", post(drain_endpoint))
+            .route("/admin/splits", get(get_splits).post(set_split))
+            .route(
+                "/admin/maintenance",
+                get(get_maintenance).post(set_maintenance),
+            );
+
+        let (router, admin_router) = match admin_address {
+            Some(_) => (Router::new(), Some(admin_router)),
+            None => (admin_router, None),
+        };
+
+        let router = router
             .fallback(routeme)
-            .with_state(app_state)
-            .layer(cors);
+            .with_state(app_state.clone())
+            .layer(cors.clone());
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
+
+        let (admin_server, admin_address) = match (admin_router, admin_address) {
+            (Some(admin_router), Some(admin_address)) => {
+                let admin_router = admin_router.with_state(app_state).layer(cors);
+                let listener = tokio::net::TcpListener::bind(admin_address).await?;
+                let admin_address = listener.local_addr()?.to_string();
+                (
+                    Some(axum::serve(
+                        listener,
+                        admin_router.into_make_service_with_connect_info::<SocketAddr>(),
+                    )),
+                    Some(admin_address),
+                )
+            }
+            _ => (None, None),
+        };
 
-        Ok(Application { server, address })
+        Ok(Application {
+            server,
+            admin_server,
+            address,
+            admin_address,
+        })
     }
 
     pub async fn run(self) -> Result<(), std::io::Error> {
-        println!("listening on {}", &self.address);
-        self.server.await
+        tracing::info!(address = %self.address, "listening");
+        match self.admin_server {
+            Some(admin_server) => {
+                if let Some(admin_address) = &self.admin_address {
+                    tracing::info!(address = %admin_address, "admin listening");
+                }
+                tokio::try_join!(self.server, admin_server).map(|_| ())
+            }
+            None => self.server.await,
+        }
     }
 }
 
@@ -72,6 +145,29 @@ impl IntoResponse for RouterError {
             }
             RouterError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             RouterError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            RouterError::InsufficientScope => (StatusCode::FORBIDDEN, "Insufficient scope"),
+            RouterError::EndpointAlreadyExists => (StatusCode::CONFLICT, "Endpoint already exists"),
+            RouterError::EndpointNotFound => (StatusCode::NOT_FOUND, "Endpoint not found"),
+            RouterError::PoolNotFound => (StatusCode::NOT_FOUND, "Pool not found"),
+            RouterError::RouteNotFound => (StatusCode::NOT_FOUND, "Route not found"),
+            RouterError::InvalidSplit => (
+                StatusCode::BAD_REQUEST,
+                "A split needs weights for known pools other than the route's mirror, at least one above zero and adding up to at most 4294967295",
+            ),
+            RouterError::UnknownHost => (StatusCode::NOT_FOUND, "Unknown host"),
+            RouterError::MisdirectedRequest => {
+                (StatusCode::MISDIRECTED_REQUEST, "Misdirected request")
+            }
+            RouterError::InvalidEndpoint => (StatusCode::BAD_REQUEST, "Invalid endpoint URI"),
+            RouterError::PayloadTooLarge => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large")
+            }
+            RouterError::NoEndpointAvailable => {
+                (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable")
+            }
+            RouterError::BadGateway => (StatusCode::BAD_GATEWAY, "Bad gateway"),
+            RouterError::GatewayTimeout => (StatusCode::GATEWAY_TIMEOUT, "Gateway timeout"),
+            RouterError::Maintenance => (StatusCode::SERVICE_UNAVAILABLE, "Down for maintenance"),
             RouterError::UnexpectedError => (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error"),
         };
         let body = Json(ErrorResponse {
@@ -1,30 +1,91 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
 use roundest_robin_router::{
-    app_state::AppState,
+    app_state::{AppState, DockerStatsCache, EndpointStoreType},
     domain::{Endpoint, EndpointStore},
-    services::hashmap_endpoint_store::HashmapEndpointStore,
-    utils::constants::prod,
+    services::{
+        access_log::AccessLogger,
+        autoscaler::{AutoscaleSettings, Autoscaler},
+        balancing::BalancingStrategy,
+        btreemap_endpoint_store::BTreeMapEndpointStore,
+        discovery::{
+            dns::{DnsDiscovery, DnsQuery},
+            docker::DockerDiscovery,
+            file::FileDiscovery,
+        },
+        docker::{connect_docker, DockerSettings},
+        docker_stats_collector::DockerStatsCollector,
+        error_pages::ErrorPages,
+        events::{EventBus, EventPublisher},
+        health_check::HealthChecker,
+        pools::{Pools, DEFAULT_POOL},
+        route_config::RouteConfig,
+        routing::RouteTable,
+        telemetry::{init_tracing, TelemetrySettings},
+    },
+    utils::{
+        auth::AuthSettings,
+        constants::{
+            env, prod, ADMIN_ADDRESS, AUTOSCALE_POOL, DNS_DISCOVERY_INTERVAL_SECS,
+            DNS_DISCOVERY_NAME, DNS_DISCOVERY_POOL, DNS_DISCOVERY_PORT, DNS_DISCOVERY_SCHEME,
+            DOCKER_DISCOVERY_INTERVAL_SECS, DOCKER_DISCOVERY_POOL, DOCKER_STATS_INTERVAL_SECS,
+            EVENTS_INTERVAL_SECS, FILE_DISCOVERY_INTERVAL_SECS, FILE_DISCOVERY_PATH,
+            FILE_DISCOVERY_POOL, ROUTES_CONFIG,
+        },
+    },
     Application,
 };
 
+/// The store of the pool `name`, which the `setting` environment variable chose.
+fn pool_store(pools: &Pools, name: &str, setting: &str) -> EndpointStoreType {
+    pools
+        .get(name)
+        .unwrap_or_else(|| panic!("{} names an unknown pool: {}", setting, name))
+        .store
+        .clone()
+}
+
+/// Docker discovery and autoscaling name their pool by container label. Without a routes
+/// config the router has only the default pool, and that label feeds it.
+fn docker_pool_store(pools: &Pools, label: &str, setting: &str) -> EndpointStoreType {
+    match ROUTES_CONFIG.as_deref() {
+        Some(_) => pool_store(pools, label, setting),
+        None => pools.default_pool().store.clone(),
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    let endpoint_store = Arc::new(RwLock::new(HashmapEndpointStore::default()));
+    let tracer_provider = init_tracing(&TelemetrySettings::from_env());
 
-    for port in 7001..=7005 {
-        // PURELY FOR TESTING PURPOSES - MAKE THIS REAL
-        let uri = format!("http://localhost:{}", port).parse().unwrap();
-        let endpoint = Endpoint::new(uri);
+    let mut default_store =
+        BTreeMapEndpointStore::default().with_strategy(BalancingStrategy::from_env());
 
-        endpoint_store
-            .write()
-            .await
-            .add_endpoint(endpoint)
-            .await
-            .unwrap();
-    }
+    let (pools, routes) = match ROUTES_CONFIG.as_deref() {
+        Some(path) => {
+            let config = RouteConfig::load(path)
+                .unwrap_or_else(|e| panic!("Failed to load {}: {}", path, e));
+            config
+                .build(default_store)
+                .await
+                .unwrap_or_else(|e| panic!("Failed to load {}: {}", path, e))
+        }
+        None => {
+            for port in 7001..=7005 {
+                // PURELY FOR TESTING PURPOSES - MAKE THIS REAL
+                let uri = format!("http://localhost:{}", port).parse().unwrap();
+                let endpoint = Endpoint::new(uri);
+
+                default_store.add_endpoint(endpoint).await.unwrap();
+            }
+            (
+                Pools::single(Arc::new(RwLock::new(default_store))),
+                RouteTable::new(Vec::new(), DEFAULT_POOL),
+            )
+        }
+    };
+    let endpoint_store = pools.default_pool().store.clone();
 
     // // // bad endpoint for testing failed server scenario
     // // let bad_uri = "http://localhost:7005".parse().unwrap();
@@ -38,11 +99,132 @@ async fn main() {
     //     .await
     //     .unwrap();
 
-    let app_state = AppState::new(endpoint_store);
+    // Docker is optional: without it the router runs and simply omits container stats
+    let docker = match DockerSettings::from_env().map(|settings| connect_docker(&settings)) {
+        Some(Ok(docker)) => {
+            if let Err(e) = docker.ping().await {
+                tracing::warn!(error = %e, "docker daemon not reachable yet");
+            }
+            Some(docker)
+        }
+        Some(Err(e)) => {
+            tracing::warn!(error = %e, "docker disabled, failed to configure client");
+            None
+        }
+        None => None,
+    };
+
+    if let Some(pool) = DOCKER_DISCOVERY_POOL.as_deref() {
+        let docker = docker
+            .clone()
+            .expect("Docker service discovery requires a Docker connection");
+        DockerDiscovery::new(
+            docker,
+            pool,
+            Duration::from_secs(*DOCKER_DISCOVERY_INTERVAL_SECS),
+            docker_pool_store(&pools, pool, env::DOCKER_DISCOVERY_POOL_ENV_VAR),
+        )
+        .spawn();
+    }
+
+    if let Some(name) = DNS_DISCOVERY_NAME.as_deref() {
+        // a port means plain A/AAAA records, otherwise the name is an SRV record
+        let query = match *DNS_DISCOVERY_PORT {
+            Some(port) => DnsQuery::Host {
+                name: name.to_string(),
+                port,
+            },
+            None => DnsQuery::Srv {
+                name: name.to_string(),
+            },
+        };
+        let (config, options) = hickory_resolver::system_conf::read_system_conf()
+            .expect("Failed to read system DNS configuration");
+        DnsDiscovery::new(
+            DnsDiscovery::resolver(config, options),
+            query,
+            &DNS_DISCOVERY_SCHEME,
+            Duration::from_secs(*DNS_DISCOVERY_INTERVAL_SECS),
+            DNS_DISCOVERY_POOL
+                .as_deref()
+                .map_or(endpoint_store.clone(), |pool| {
+                    pool_store(&pools, pool, env::DNS_DISCOVERY_POOL_ENV_VAR)
+                }),
+        )
+        .spawn();
+    }
 
-    let app = Application::build(app_state, prod::APP_ADDRESS)
+    if let Some(path) = FILE_DISCOVERY_PATH.as_deref() {
+        FileDiscovery::new(
+            path,
+            Duration::from_secs(*FILE_DISCOVERY_INTERVAL_SECS),
+            FILE_DISCOVERY_POOL
+                .as_deref()
+                .map_or(endpoint_store.clone(), |pool| {
+                    pool_store(&pools, pool, env::FILE_DISCOVERY_POOL_ENV_VAR)
+                }),
+        )
+        .spawn();
+    }
+
+    let docker_stats = DockerStatsCache::default();
+    if let Some(docker) = docker.clone() {
+        pools
+            .iter()
+            .fold(
+                DockerStatsCollector::new(
+                    docker,
+                    Duration::from_secs(*DOCKER_STATS_INTERVAL_SECS),
+                    docker_stats.clone(),
+                ),
+                |collector, pool| collector.with_endpoint_store(pool.store.clone()),
+            )
+            .spawn();
+    }
+
+    if let Some(pool) = AUTOSCALE_POOL.as_deref() {
+        let docker = docker.expect("Autoscaling requires a Docker connection");
+        Autoscaler::new(
+            docker,
+            AutoscaleSettings::from_env(pool),
+            docker_pool_store(&pools, pool, env::AUTOSCALE_POOL_ENV_VAR),
+            docker_stats.clone(),
+        )
+        .spawn();
+    }
+
+    let events = EventBus::default();
+    EventPublisher::new(
+        Arc::new(pools.clone()),
+        events.clone(),
+        Duration::from_secs(*EVENTS_INTERVAL_SECS),
+    )
+    .spawn();
+
+    let app_state = AppState::new(endpoint_store, AuthSettings::from_env())
+        .with_docker_stats(docker_stats)
+        .with_access_log(AccessLogger::from_env().expect("Failed to open access log"))
+        .with_events(events)
+        .with_error_pages(ErrorPages::from_env().expect("Failed to load error pages"))
+        .with_routes(pools.clone(), routes);
+
+    for pool in pools.iter() {
+        if let Some(settings) = pool.health_check.clone() {
+            HealthChecker::new(&pool.name, pool.store.clone(), settings)
+                .with_metrics(app_state.metrics.clone())
+                .with_events(app_state.events.clone())
+                .spawn();
+        }
+    }
+
+    let app = Application::build(app_state, prod::APP_ADDRESS, ADMIN_ADDRESS.as_deref())
         .await
         .expect("Failed to build app");
 
-    app.run().await.expect("Failed to run app");
+    let result = app.run().await;
+
+    if let Some(tracer_provider) = tracer_provider {
+        tracer_provider.force_flush();
+    }
+    result.expect("Failed to run app");
 }
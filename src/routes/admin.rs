@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use axum::{extract::State, http::StatusCode, http::Uri, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::{AppState, EndpointStoreType},
+    domain::{Endpoint, EndpointStoreError, RouterError},
+    utils::auth::scopes,
+};
+
+use super::AdminClaims;
+
+#[derive(Debug, Deserialize)]
+pub struct EndpointRequest {
+    pub uri: String,
+    /// Defaults to the default pool.
+    pub pool: Option<String>,
+}
+
+pub async fn add_endpoint(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+    Json(request): Json<EndpointRequest>,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::ENDPOINTS_WRITE)?;
+
+    let uri = parse_endpoint_uri(&request.uri)?;
+    pool_store(&state, &request)?
+        .write()
+        .await
+        .add_endpoint(Endpoint::new(uri))
+        .await
+        .map_err(|e| match e {
+            EndpointStoreError::EndpointAlreadyExists => RouterError::EndpointAlreadyExists,
+            _ => RouterError::UnexpectedError,
+        })?;
+
+    Ok(StatusCode::CREATED)
+}
+
+pub async fn enable_endpoint(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+    Json(request): Json<EndpointRequest>,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::ENDPOINTS_WRITE)?;
+
+    let endpoint = find_endpoint(&state, &request).await?;
+    endpoint.cancel_drain();
+    endpoint.activate();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn disable_endpoint(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+    Json(request): Json<EndpointRequest>,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::ENDPOINTS_WRITE)?;
+
+    find_endpoint(&state, &request).await?.deactivate();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Stop routing new requests to the endpoint; in-flight requests finish normally.
+pub async fn drain_endpoint(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+    Json(request): Json<EndpointRequest>,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::ENDPOINTS_WRITE)?;
+
+    find_endpoint(&state, &request).await?.drain();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SplitRequest {
+    /// Name of a route with a split.
+    pub route: String,
+    pub weights: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SplitStatus {
+    pub route: String,
+    pub weights: BTreeMap<String, u32>,
+    pub header: Option<String>,
+    pub cookie: Option<String>,
+}
+
+pub async fn get_splits(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::STATS_READ)?;
+
+    let splits: Vec<SplitStatus> = state
+        .routes
+        .routes()
+        .iter()
+        .filter_map(|route| {
+            let split = route.split.as_ref()?;
+            Some(SplitStatus {
+                route: route.name.clone(),
+                weights: split.weights().into_iter().collect(),
+                header: split.header.as_ref().map(|header| header.to_string()),
+                cookie: split.cookie.clone(),
+            })
+        })
+        .collect();
+    Ok(Json(splits))
+}
+
+/// Replace a route's split weights. Pools left out leave the split, and can no longer be
+/// forced; every pool must already exist, and none may be the route's mirror pool.
+pub async fn set_split(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+    Json(request): Json<SplitRequest>,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::ROUTES_WRITE)?;
+
+    let route = state
+        .routes
+        .route(&request.route)
+        .ok_or(RouterError::RouteNotFound)?;
+    let split = route.split.as_ref().ok_or(RouterError::RouteNotFound)?;
+    if request
+        .weights
+        .keys()
+        .any(|pool| state.pools.get(pool).is_none())
+    {
+        return Err(RouterError::PoolNotFound);
+    }
+    // the shadow pool must not also serve the route, as when it was loaded
+    if let Some(mirror) = &route.mirror {
+        if request.weights.contains_key(&mirror.pool) {
+            return Err(RouterError::InvalidSplit);
+        }
+    }
+    if !split.set_weights(request.weights.clone().into_iter().collect()) {
+        return Err(RouterError::InvalidSplit);
+    }
+
+    tracing::info!(route = %request.route, weights = ?request.weights, "split weights changed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+    pub since: Option<DateTime<Utc>>,
+}
+
+fn maintenance_status(state: &AppState) -> MaintenanceStatus {
+    let since = state.error_pages.maintenance_since();
+    MaintenanceStatus {
+        enabled: since.is_some(),
+        since,
+    }
+}
+
+pub async fn get_maintenance(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::STATS_READ)?;
+    Ok(Json(maintenance_status(&state)))
+}
+
+/// Switch maintenance mode. Proxied requests then get the maintenance page, while health
+/// checks and discovery carry on so the pools are ready when it is switched off.
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+    Json(request): Json<MaintenanceRequest>,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::ROUTES_WRITE)?;
+
+    state.error_pages.set_maintenance(request.enabled);
+    tracing::info!(enabled = request.enabled, "maintenance mode changed");
+    Ok(Json(maintenance_status(&state)))
+}
+
+fn parse_endpoint_uri(uri: &str) -> Result<Uri, RouterError> {
+    Endpoint::parse_uri(uri).ok_or(RouterError::InvalidEndpoint)
+}
+
+fn pool_store(
+    state: &AppState,
+    request: &EndpointRequest,
+) -> Result<EndpointStoreType, RouterError> {
+    let pool = match &request.pool {
+        Some(name) => state.pools.get(name).ok_or(RouterError::PoolNotFound)?,
+        None => state.pools.default_pool(),
+    };
+    Ok(pool.store.clone())
+}
+
+async fn find_endpoint(
+    state: &AppState,
+    request: &EndpointRequest,
+) -> Result<Endpoint, RouterError> {
+    let uri = parse_endpoint_uri(&request.uri)?;
+    let endpoints = pool_store(state, request)?
+        .read()
+        .await
+        .get_all_endpoints()
+        .await
+        .map_err(|_| RouterError::UnexpectedError)?;
+
+    // endpoints share their counters and state through Arcs, so the clone acts on the stored one
+    endpoints
+        .into_iter()
+        .find(|ep| ep.uri == uri)
+        .ok_or(RouterError::EndpointNotFound)
+}
@@ -0,0 +1,45 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use axum_extra::extract::CookieJar;
+
+use crate::{
+    app_state::AppState,
+    domain::RouterError,
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME},
+};
+
+/// Validated admin token, taken from an `Authorization: Bearer` header or the `jwt` cookie.
+pub struct AdminClaims(pub Claims);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AdminClaims {
+    type Rejection = RouterError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = bearer_token(&parts.headers)
+            .or_else(|| {
+                CookieJar::from_headers(&parts.headers)
+                    .get(JWT_COOKIE_NAME)
+                    .map(|cookie| cookie.value().to_owned())
+            })
+            .ok_or(RouterError::MissingToken)?;
+
+        state.auth.validate_token(&token).map(AdminClaims)
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty() {
+        Some(token.trim().to_owned())
+    } else {
+        None
+    }
+}
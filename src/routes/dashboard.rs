@@ -0,0 +1,19 @@
+use axum::{http::header::CONTENT_TYPE, response::Html, response::IntoResponse};
+
+/// The dashboard is compiled in so the binary serves it from any working directory.
+const INDEX_HTML: &str = include_str!("../../assets/index.html");
+const APP_JS: &str = include_str!("../../assets/app.js");
+const DASHBOARD_CSS: &str = include_str!("../../assets/dashboard.css");
+
+/// Static page only; the data and actions it calls are authorized like any admin request.
+pub async fn get_dashboard() -> impl IntoResponse {
+    Html(INDEX_HTML)
+}
+
+pub async fn get_dashboard_js() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "text/javascript; charset=utf-8")], APP_JS)
+}
+
+pub async fn get_dashboard_css() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "text/css; charset=utf-8")], DASHBOARD_CSS)
+}
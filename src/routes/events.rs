@@ -0,0 +1,53 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{stream, Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::AdminClaims;
+use crate::{
+    app_state::AppState, domain::RouterError, services::events::RouterEvent, utils::auth::scopes,
+};
+
+/// Server-Sent Events feed of endpoint changes and stats deltas.
+///
+/// Starts with the current state of every endpoint, then streams events as they happen.
+pub async fn get_events(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, RouterError> {
+    claims.require_scope(scopes::STATS_READ)?;
+
+    // subscribe first so nothing between the snapshot and the feed is lost
+    let receiver = state.events.subscribe();
+    let initial: Vec<RouterEvent> = state
+        .pools
+        .all_endpoints()
+        .await
+        .iter()
+        .map(|(pool, endpoint)| RouterEvent::endpoint_state(pool, endpoint))
+        .collect();
+
+    let feed = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "event subscriber fell behind");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    let events = stream::iter(initial).chain(feed).map(|event| {
+        Ok(Event::default()
+            .event(event.name())
+            .data(serde_json::to_string(&event).unwrap_or_default()))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,28 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+
+use super::AdminClaims;
+use crate::{app_state::AppState, domain::RouterError, utils::auth::scopes};
+
+/// Prometheus scrape target, in the text exposition format.
+pub async fn get_metrics(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::STATS_READ)?;
+
+    let endpoints = state.pools.all_endpoints().await;
+    let body = state
+        .metrics
+        .render(&endpoints, state.access_log.dropped_lines())
+        .map_err(|_| RouterError::UnexpectedError)?;
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        body,
+    ))
+}
@@ -1,3 +1,13 @@
+mod admin;
+mod auth;
+mod dashboard;
+mod events;
+mod metrics;
 mod router;
 
+pub use admin::*;
+pub use auth::*;
+pub use dashboard::*;
+pub use events::*;
+pub use metrics::*;
 pub use router::*;
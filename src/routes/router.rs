@@ -1,114 +1,486 @@
 use axum::{
-    body::Body,
-    extract::{Request, State},
-    http::StatusCode,
+    body::{Body, HttpBody},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use chrono::Utc;
+use http_body_util::LengthLimitError;
 use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
 
-use crate::{app_state::AppState, domain::RouterError};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use super::AdminClaims;
+use crate::{
+    app_state::AppState,
+    domain::{Endpoint, RouterError, WindowStats},
+    services::{
+        access_log::AccessLogEntry,
+        error_pages::ErrorPage,
+        events::RouterEvent,
+        headers::{forwardable, TemplateVars},
+        mirror::{self, MirroredRequest, PrimaryOutcome},
+        routing::{upstream_url, RouteRequest},
+        telemetry::{extract_context, inject_context},
+    },
+    utils::{
+        auth::scopes,
+        constants::{PROXY_MAX_BODY_BYTES, PROXY_MAX_RETRIES, PROXY_TIMEOUT_MS, REQUEST_ID_HEADER},
+    },
+};
 
 pub async fn routeme(
     State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     request: Request<Body>,
-) -> Result<impl IntoResponse, RouterError> {
-    let endpoint_store = &state.endpoint_store.read().await;
-
-    // check for dead servers before selecting next endpoint
-    endpoint_store.check_for_dead_servers().await;
-    let end_point = match endpoint_store.get_next_endpoint().await {
-        Ok(end_point) => {
-            end_point.increase_concurrent_connection_count();
-            end_point
-        }
-        Err(_) => {
-            return Err(RouterError::IncorrectCredentials);
-        }
+) -> Response {
+    let started = Instant::now();
+    let request_id = request_id(&request);
+    let mut entry = access_log_entry(&request, client, &request_id);
+
+    let span = tracing::info_span!(
+        "request",
+        otel.kind = "server",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %entry.path,
+        pool = tracing::field::Empty,
+    );
+    span.set_parent(extract_context(request.headers()));
+    let accept = request.headers().get(header::ACCEPT).cloned();
+    let mut response = match proxy(&state, request, &request_id, &mut entry)
+        .instrument(span.clone())
+        .await
+    {
+        Ok(response) => response,
+        Err(error) => match ErrorPage::for_error(&error) {
+            Some(page) => state.error_pages.response(page, accept.as_ref()),
+            None => error.into_response(),
+        },
     };
-    // println!(
-    //     "Forwarding request to endpoint: {} {}",
-    //     end_point.uri,
-    //     request.uri()
-    // );
+    span.in_scope(|| tracing::debug!(status = response.status().as_u16(), "request finished"));
 
-    // Make HTTP request to the endpoint's URI
-    let client = reqwest::Client::new();
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    if state.access_log.should_log(&entry.path) {
+        entry.status = response.status().as_u16();
+        entry.total_latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+        state.access_log.log(&entry);
+    }
+    response
+}
+
+/// The caller's `X-Request-Id` if it looks sane, otherwise a fresh one.
+fn request_id(request: &Request<Body>) -> String {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
 
-    let combined_uri_string = format!(
-        "{}{}",
-        end_point.uri,
+fn access_log_entry(
+    request: &Request<Body>,
+    client: SocketAddr,
+    request_id: &str,
+) -> AccessLogEntry {
+    let header = |name: header::HeaderName| {
         request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+
+    AccessLogEntry {
+        timestamp: Utc::now(),
+        request_id: request_id.to_string(),
+        client_ip: Some(client.ip()),
+        method: request.method().to_string(),
+        path: request
             .uri()
             .path_and_query()
-            .map(|pq| pq.as_str())
-            .unwrap_or("")
-    );
+            .map(|pq| pq.to_string())
+            .unwrap_or_else(|| "/".to_string()),
+        protocol: format!("{:?}", request.version()),
+        status: 0,
+        // the declared length, until the proxied body has been read
+        bytes_in: header(header::CONTENT_LENGTH)
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0),
+        bytes_out: 0,
+        upstream: None,
+        upstream_latency_ms: None,
+        total_latency_ms: 0.0,
+        retries: 0,
+        referer: header(header::REFERER),
+        user_agent: header(header::USER_AGENT),
+    }
+}
 
-    let response = match client
-        .get(combined_uri_string)
-        // .json(&request) // Forward the login request
-        .send()
+/// Forward the request, noting the upstream, retries and response size in `entry`.
+async fn proxy(
+    state: &AppState,
+    request: Request<Body>,
+    request_id: &str,
+    entry: &mut AccessLogEntry,
+) -> Result<Response, RouterError> {
+    let route_request = RouteRequest::new(&request);
+    let host = route_request.host.clone();
+    let route = state
+        .routes
+        .resolve(&route_request)
+        .map_err(|status| match status {
+            StatusCode::MISDIRECTED_REQUEST => RouterError::MisdirectedRequest,
+            _ => RouterError::UnknownHost,
+        })?;
+    if let Some((route, action)) = route.and_then(|route| Some((route, route.action.as_ref()?))) {
+        let mut response =
+            action.response(&route.matcher, request.uri().path(), request.uri().query());
+        let vars = TemplateVars {
+            client_ip: entry.client_ip,
+            request_id,
+            host: host.as_deref(),
+            method: request.method().as_str(),
+            path: request.uri().path(),
+            route: &route.name,
+            pool: "",
+            upstream: None,
+        };
+        for policies in [state.routes.headers(), &route.headers] {
+            policies.response.apply(response.headers_mut(), &vars);
+        }
+        entry.bytes_out = response.body().size_hint().exact().unwrap_or(0);
+        return Ok(response);
+    }
+    // pools stay health checked, so traffic can resume as soon as this is switched off
+    if state.error_pages.maintenance_since().is_some() {
+        return Err(RouterError::Maintenance);
+    }
+    let pool = route
+        .and_then(|route| state.pools.get(&route.choose_pool(&route_request)))
+        .unwrap_or_else(|| state.pools.default_pool());
+    tracing::Span::current().record("pool", pool.name.as_str());
+
+    let request_started = Instant::now();
+
+    // buffered so a retry can send it again
+    let (parts, body) = request.into_parts();
+    let body = axum::body::to_bytes(body, *PROXY_MAX_BODY_BYTES)
         .await
-    {
-        Ok(response) => {
-            end_point.decrease_concurrent_connection_count();
-            end_point.incr_success();
-            response
+        .map_err(|e| {
+            if e.into_inner().is::<LengthLimitError>() {
+                RouterError::PayloadTooLarge
+            } else {
+                RouterError::UnexpectedError
+            }
+        })?;
+    entry.bytes_in = body.len() as u64;
+    let method = reqwest::Method::from_bytes(parts.method.as_str().as_bytes())
+        .map_err(|_| RouterError::UnexpectedError)?;
+
+    // the backend gets its own Host, and a Content-Length for the buffered body
+    let mut forwarded = forwardable(&parts.headers, &[header::HOST, header::CONTENT_LENGTH]);
+    if let Ok(value) = HeaderValue::from_str(request_id) {
+        forwarded.insert(REQUEST_ID_HEADER, value);
+    }
+    let policies = [
+        Some(state.routes.headers()),
+        route.map(|route| &route.headers),
+    ];
+    let policies = policies.iter().flatten();
+    let vars = TemplateVars {
+        client_ip: entry.client_ip,
+        request_id,
+        host: host.as_deref(),
+        method: parts.method.as_str(),
+        path: parts.uri.path(),
+        route: route.map_or("", |route| route.name.as_str()),
+        pool: &pool.name,
+        upstream: None,
+    };
+
+    let path = match route.and_then(|route| route.rewrite.as_ref()) {
+        Some(rewrite) => {
+            let path = rewrite.apply(parts.uri.path());
+            tracing::debug!(upstream_path = %path, "rewrote path");
+            path
+        }
+        None => parts.uri.path().to_string(),
+    };
+
+    // the copy goes out alongside the primary request and is never waited on
+    let mut mirror_outcome = route.and_then(|route| {
+        let settings = route.mirror.as_ref().filter(|settings| settings.sample())?;
+        let shadow_pool = state.pools.get(&settings.pool)?.clone();
+        let mut headers = forwarded.clone();
+        for policy in policies.clone() {
+            policy.request.apply(&mut headers, &vars);
+        }
+        let (outcome, _) = mirror::spawn(
+            settings,
+            mirror::in_flight_limit(),
+            shadow_pool,
+            state.metrics.clone(),
+            MirroredRequest {
+                route: route.name.clone(),
+                request_id: request_id.to_string(),
+                method: method.clone(),
+                path: path.clone(),
+                query: parts.uri.query().map(str::to_string),
+                headers: to_reqwest_headers(&headers),
+                body: body.clone(),
+            },
+        )?;
+        Some(outcome)
+    });
+
+    // called once the request has an answer, or none is coming
+    let split_route = route.filter(|route| route.split.is_some());
+    let mut finish = |status: Option<StatusCode>| {
+        let latency = request_started.elapsed();
+        if let Some(route) = split_route {
+            state
+                .metrics
+                .observe_split(&route.name, &pool.name, status, latency);
         }
-        Err(_) => {
-            end_point.decrease_concurrent_connection_count();
-            end_point.incr_failure();
-            return Err(RouterError::UnexpectedError);
+        if let Some(outcome) = mirror_outcome.take() {
+            let _ = outcome.send(PrimaryOutcome { status, latency });
         }
     };
 
-    let response_text = response.text().await.unwrap();
+    // pools with active health checks leave this to the health checker
+    if pool.health_check.is_none() {
+        let health_checks = pool.store.read().await.check_for_dead_servers().await;
+        state.metrics.observe_ejections(&pool.name, &health_checks);
+        for check in health_checks.iter().filter(|check| !check.healthy) {
+            state.events.publish(RouterEvent::Health {
+                pool: pool.name.clone(),
+                endpoint: check.endpoint.uri.to_string(),
+                healthy: false,
+                success_count: check.endpoint.success_count(),
+                failure_count: check.endpoint.failure_count(),
+            });
+        }
+    }
+
+    // Make HTTP request to the endpoint's URI
+    let client = reqwest::Client::new();
 
-    // TODO: this thing doesn't properly pass along the request body etc.
-    // this needs to pass the content-type and other headers too into what is returned form this function
+    // requests that never got a response are retried on an endpoint not tried yet, unless
+    // the backend may have acted on a non-idempotent one already
+    let retryable = |e: &reqwest::Error| parts.method.is_idempotent() || e.is_connect();
+    let mut retries = 0;
+    let mut tried = Vec::new();
+    let mut failed: Option<(Endpoint, RouterError)> = None;
+    let (response, upstream) = loop {
+        // hold the read only to pick: kept across the upstream call, it would queue the
+        // writes of discovery and the admin API, and every new reader behind them
+        let selected = pool
+            .store
+            .read()
+            .await
+            .get_next_endpoint_excluding(&tried)
+            .instrument(tracing::info_span!("select_endpoint"))
+            .await;
+        let end_point = match selected {
+            Ok(end_point) => {
+                end_point.increase_concurrent_connection_count();
+                end_point
+            }
+            Err(_) => {
+                finish(None);
+                // every endpoint left has already failed this request
+                if let Some((_, error)) = failed {
+                    return Err(error);
+                }
+                tracing::warn!("no endpoint available");
+                return Err(RouterError::NoEndpointAvailable);
+            }
+        };
+        if let Some((failed_endpoint, _)) = failed.take() {
+            retries += 1;
+            entry.retries = retries;
+            state.metrics.observe_retry(&pool.name, &failed_endpoint);
+        }
 
-    let converted_response = Response::builder()
-        .header("content-type", "text/html; charset=utf-8")
-        .body(Body::from(response_text))
-        .unwrap();
+        let combined_uri_string = upstream_url(&end_point.uri, &path, parts.uri.query());
+        let upstream = end_point.uri.to_string();
+
+        let upstream_span = tracing::info_span!(
+            "upstream",
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            endpoint = %end_point.uri,
+            attempt = retries + 1,
+            status = tracing::field::Empty,
+        );
+        let mut request_headers = forwarded.clone();
+        let vars = TemplateVars {
+            upstream: Some(&upstream),
+            ..vars
+        };
+        for policy in policies.clone() {
+            policy.request.apply(&mut request_headers, &vars);
+        }
+        let mut request_headers = to_reqwest_headers(&request_headers);
+        inject_context(&upstream_span, &parts.headers, &mut request_headers);
 
-    Ok((StatusCode::OK, converted_response))
+        let started = Instant::now();
+        let mut upstream_request = client
+            .request(method.clone(), combined_uri_string)
+            .headers(request_headers)
+            .body(body.clone());
+        if let Some(timeout) = *PROXY_TIMEOUT_MS {
+            upstream_request = upstream_request.timeout(Duration::from_millis(timeout));
+        }
+        let result = upstream_request
+            .send()
+            .instrument(upstream_span.clone())
+            .await;
+        end_point.decrease_concurrent_connection_count();
+        entry.upstream = Some(upstream.clone());
+        entry.upstream_latency_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+
+        match result {
+            Ok(response) => {
+                upstream_span.record("status", response.status().as_u16());
+                end_point.incr_success();
+                let status = StatusCode::from_u16(response.status().as_u16()).ok();
+                end_point.record_request(started.elapsed(), response.status().is_server_error());
+                state
+                    .metrics
+                    .observe_request(&pool.name, &end_point, status, started.elapsed());
+                break (response, upstream);
+            }
+            Err(e) => {
+                upstream_span.record("otel.status_code", "ERROR");
+                upstream_span.in_scope(|| tracing::warn!(error = %e, "upstream request failed"));
+                end_point.incr_failure();
+                end_point.record_request(started.elapsed(), true);
+                state
+                    .metrics
+                    .observe_request(&pool.name, &end_point, None, started.elapsed());
+                let error = if e.is_timeout() {
+                    RouterError::GatewayTimeout
+                } else {
+                    RouterError::BadGateway
+                };
+                if retries >= *PROXY_MAX_RETRIES || !retryable(&e) {
+                    finish(None);
+                    return Err(error);
+                }
+                tried.push(end_point.uri.clone());
+                failed = Some((end_point, error));
+            }
+        }
+    };
+
+    let status = StatusCode::from_u16(response.status().as_u16())
+        .map_err(|_| RouterError::UnexpectedError)?;
+    finish(Some(status));
+    let mut headers = forwardable(
+        &from_reqwest_headers(response.headers()),
+        &[header::CONTENT_LENGTH],
+    );
+    let vars = TemplateVars {
+        upstream: Some(&upstream),
+        ..vars
+    };
+    for policy in policies {
+        policy.response.apply(&mut headers, &vars);
+    }
+
+    let response_body = response
+        .bytes()
+        .instrument(tracing::info_span!("upstream_response"))
+        .await
+        .map_err(|_| RouterError::UnexpectedError)?;
+    entry.bytes_out = response_body.len() as u64;
+
+    let mut converted_response = Response::new(Body::from(response_body));
+    *converted_response.status_mut() = status;
+    *converted_response.headers_mut() = headers;
+    Ok(converted_response)
 }
 
-pub async fn print_stats(State(state): State<AppState>) -> Result<impl IntoResponse, RouterError> {
-    let endpoint_store = &state.endpoint_store.read().await;
+/// reqwest is on an older `http` than axum, so headers are copied across by name.
+fn to_reqwest_headers(headers: &HeaderMap) -> reqwest::header::HeaderMap {
+    let mut converted = reqwest::header::HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(name.as_str().as_bytes()),
+            reqwest::header::HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
+
+fn from_reqwest_headers(headers: &reqwest::header::HeaderMap) -> HeaderMap {
+    let mut converted = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_str().as_bytes()),
+            HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            converted.append(name, value);
+        }
+    }
+    converted
+}
+
+pub async fn print_stats(
+    State(state): State<AppState>,
+    AdminClaims(claims): AdminClaims,
+) -> Result<impl IntoResponse, RouterError> {
+    claims.require_scope(scopes::STATS_READ)?;
+
+    let endpoints = state.pools.all_endpoints().await;
+
+    // served from the collector's cache; empty when Docker is disabled or unreachable
+    let container_stats = state.docker_stats.read().await;
 
-    let endpoints = endpoint_store.get_all_endpoints().await.unwrap();
-    let container_stats = crate::domain::get_docker_stats().await.unwrap();
     let stats: Vec<EndpointStats> = endpoints
         .into_iter()
-        .map(|ep| EndpointStats {
-            uri: ep.uri.to_string(),
-            count_success: ep.count_success.load(std::sync::atomic::Ordering::Relaxed),
-            count_failure: ep.count_failure.load(std::sync::atomic::Ordering::Relaxed),
-            count_concurrent_connections: ep
-                .count_concurrent_connections
-                .load(std::sync::atomic::Ordering::Relaxed),
-            active_server: ep.active_server.load(std::sync::atomic::Ordering::Relaxed),
-            cpu_percentage: container_stats
-                .get(&ep.uri.to_string())
-                .map_or(0.0, |stats| stats.cpu_percentage),
-            memory_usage: container_stats
-                .get(&ep.uri.to_string())
-                .map_or(0, |stats| stats.memory_usage.try_into().unwrap()),
-            memory_limit: container_stats
-                .get(&ep.uri.to_string())
-                .map_or(0, |stats| stats.memory_limit.try_into().unwrap()),
-            memory_percentage: container_stats
-                .get(&ep.uri.to_string())
-                .map_or(0.0, |stats| stats.memory_percentage),
-            network_rx_bytes: container_stats
-                .get(&ep.uri.to_string())
-                .map_or(0, |stats| stats.network_rx_bytes.try_into().unwrap()),
-            network_tx_bytes: container_stats
-                .get(&ep.uri.to_string())
-                .map_or(0, |stats| stats.network_tx_bytes.try_into().unwrap()),
+        .map(|(pool, ep)| {
+            let container = container_stats.for_endpoint(&ep.uri);
+            EndpointStats {
+                pool,
+                uri: ep.uri.to_string(),
+                count_success: ep.count_success.load(std::sync::atomic::Ordering::Relaxed),
+                count_failure: ep.count_failure.load(std::sync::atomic::Ordering::Relaxed),
+                count_concurrent_connections: ep
+                    .count_concurrent_connections
+                    .load(std::sync::atomic::Ordering::Relaxed),
+                active_server: ep.active_server.load(std::sync::atomic::Ordering::Relaxed),
+                draining: ep.is_draining(),
+                weight: ep.weight(),
+                windows: ep.request_windows(),
+                container_id: container.map(|stats| stats.container_id.clone()),
+                container_name: container.map(|stats| stats.container_name.clone()),
+                cpu_percentage: container.map(|stats| stats.cpu_percentage),
+                memory_usage: container.map(|stats| stats.memory_usage as usize),
+                memory_limit: container.map(|stats| stats.memory_limit as usize),
+                memory_percentage: container.map(|stats| stats.memory_percentage),
+                network_rx_bytes: container.map(|stats| stats.network_rx_bytes as usize),
+                network_tx_bytes: container.map(|stats| stats.network_tx_bytes as usize),
+                block_read_bytes: container.map(|stats| stats.block_read_bytes as usize),
+                block_write_bytes: container.map(|stats| stats.block_write_bytes as usize),
+                pids: container.map(|stats| stats.pids as usize),
+            }
         })
         .collect();
 
@@ -117,17 +489,38 @@ pub async fn print_stats(State(state): State<AppState>) -> Result<impl IntoRespo
 
 #[derive(Debug, Serialize)]
 pub struct EndpointStats {
+    pub pool: String,
     pub uri: String,
     pub count_success: usize,
     pub count_failure: usize,
     pub count_concurrent_connections: usize,
     pub active_server: bool,
-    pub cpu_percentage: f64,
-    pub memory_usage: usize,
-    pub memory_limit: usize,
-    pub memory_percentage: f64,
-    pub network_rx_bytes: usize,
-    pub network_tx_bytes: usize,
+    pub draining: bool,
+    pub weight: usize,
+    /// Request rate, error rate and latency percentiles over the last 1, 5 and 15 minutes.
+    pub windows: Vec<WindowStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_percentage: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_usage: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_percentage: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_rx_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_tx_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_read_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_write_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pids: Option<usize>,
 }
 
 #[derive(Debug, Serialize)]
@@ -143,3 +536,104 @@ pub struct TwoFactorAuthResponse {
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{http::Uri, routing::get, Router};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{
+        app_state::EndpointStoreType, domain::EndpointStore,
+        services::btreemap_endpoint_store::BTreeMapEndpointStore, utils::auth::AuthSettings,
+    };
+
+    /// A backend answering every `GET /` with 200 after `delay`.
+    async fn backend(delay: Duration) -> Uri {
+        let app = Router::new().route(
+            "/",
+            get(move || async move {
+                tokio::time::sleep(delay).await;
+                StatusCode::OK
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        uri.parse().unwrap()
+    }
+
+    async fn state_with(endpoints: Vec<Endpoint>) -> (AppState, EndpointStoreType) {
+        let mut store = BTreeMapEndpointStore::default();
+        for endpoint in endpoints {
+            store.add_endpoint(endpoint).await.unwrap();
+        }
+        let store: EndpointStoreType = Arc::new(RwLock::new(store));
+        let state = AppState::new(store.clone(), AuthSettings::new("secret", "iss", "aud"));
+        (state, store)
+    }
+
+    async fn get_root(state: &AppState) -> Result<Response, RouterError> {
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let mut entry = access_log_entry(&request, ([127, 0, 0, 1], 0).into(), "test");
+        proxy(state, request, "test", &mut entry).await
+    }
+
+    fn request_with_id(id: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri("/");
+        if let Some(id) = id {
+            builder = builder.header(REQUEST_ID_HEADER, id);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_request_id_is_honoured_or_generated() {
+        assert_eq!(request_id(&request_with_id(Some("abc-123"))), "abc-123");
+
+        for id in [
+            None,
+            Some(""),
+            Some("has space"),
+            Some(&"x".repeat(129)[..]),
+        ] {
+            let generated = request_id(&request_with_id(id));
+            assert!(Uuid::parse_str(&generated).is_ok(), "{:?}", id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bytes_in_counts_a_chunked_body() {
+        let uri = backend(Duration::ZERO).await;
+        let (state, _) = state_with(vec![Endpoint::new(uri)]).await;
+
+        let chunks = ["hello ", "chunked ", "world"].map(Ok::<_, std::io::Error>);
+        let request = Request::post("/")
+            .body(Body::from_stream(futures_util::stream::iter(chunks)))
+            .unwrap();
+        let mut entry = access_log_entry(&request, ([127, 0, 0, 1], 0).into(), "test");
+        assert_eq!(entry.bytes_in, 0);
+
+        proxy(&state, request, "test", &mut entry).await.unwrap();
+        assert_eq!(entry.bytes_in, 19);
+    }
+
+    #[tokio::test]
+    async fn test_store_is_not_locked_during_upstream_call() {
+        let uri = backend(Duration::from_millis(500)).await;
+        let (state, store) = state_with(vec![Endpoint::new(uri)]).await;
+
+        let request = tokio::spawn(async move { get_root(&state).await.map(|r| r.status()) });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // what discovery and the admin API take to change the pool
+        let write = tokio::time::timeout(Duration::from_millis(100), store.write()).await;
+        assert!(
+            write.is_ok(),
+            "store stayed locked while the backend answered"
+        );
+        drop(write);
+        assert_eq!(request.await.unwrap().unwrap(), StatusCode::OK);
+    }
+}
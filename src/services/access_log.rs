@@ -0,0 +1,407 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    net::IpAddr,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+use crate::utils::constants::{
+    ACCESS_LOG, ACCESS_LOG_EXCLUDE_PATHS, ACCESS_LOG_FORMAT, ACCESS_LOG_MAX_BYTES,
+    ACCESS_LOG_MAX_FILES, ACCESS_LOG_SAMPLE_RATE,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessLogFormat {
+    Json,
+    /// NCSA Common Log Format.
+    Common,
+    /// Common Log Format plus referer and user agent, as nginx and Apache write by default.
+    Combined,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessLogSink {
+    Off,
+    Stdout,
+    /// Rotated to `path.1` … `path.<max_files>` once it would grow past `max_bytes`.
+    File {
+        path: PathBuf,
+        max_bytes: u64,
+        max_files: usize,
+    },
+}
+
+/// One proxied request.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub request_id: String,
+    pub client_ip: Option<IpAddr>,
+    pub method: String,
+    /// Path and query as requested.
+    pub path: String,
+    pub protocol: String,
+    pub status: u16,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Last endpoint tried; `None` if no endpoint was available.
+    pub upstream: Option<String>,
+    pub upstream_latency_ms: Option<f64>,
+    pub total_latency_ms: f64,
+    pub retries: usize,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AccessLogEntry {
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Json => serde_json::to_string(self).unwrap_or_default(),
+            AccessLogFormat::Common => self.common(),
+            AccessLogFormat::Combined => format!(
+                "{} \"{}\" \"{}\"",
+                self.common(),
+                escape(self.referer.as_deref().unwrap_or("-")),
+                escape(self.user_agent.as_deref().unwrap_or("-"))
+            ),
+        }
+    }
+
+    fn common(&self) -> String {
+        let bytes_out = match self.bytes_out {
+            0 => "-".to_string(),
+            bytes => bytes.to_string(),
+        };
+        format!(
+            "{} - - [{}] \"{} {} {}\" {} {}",
+            self.client_ip
+                .map(|ip| ip.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            self.timestamp.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            escape(&self.path),
+            self.protocol,
+            self.status,
+            bytes_out
+        )
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Lines waiting for a slow sink; further lines are dropped until it catches up.
+const BUFFER_LINES: usize = 10_000;
+
+/// Writes access log lines, subject to sampling and path exclusions.
+///
+/// Writes happen on a background task so request handling never waits on the sink. Lines
+/// that find the buffer full are dropped and counted instead.
+pub struct AccessLogger {
+    format: AccessLogFormat,
+    sample_rate: f64,
+    excluded_paths: Vec<String>,
+    output: Option<mpsc::Sender<String>>,
+    dropped: AtomicU64,
+}
+
+enum Writer {
+    Stdout(tokio::io::Stdout),
+    File(RotatingFile),
+}
+
+impl Writer {
+    async fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        match self {
+            Writer::Stdout(stdout) => {
+                stdout.write_all(format!("{}\n", line).as_bytes()).await?;
+                stdout.flush().await
+            }
+            Writer::File(file) => file.write_line(line),
+        }
+    }
+}
+
+impl AccessLogger {
+    pub fn disabled() -> Self {
+        Self {
+            format: AccessLogFormat::Json,
+            sample_rate: 0.0,
+            excluded_paths: Vec::new(),
+            output: None,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Must be called from within a Tokio runtime unless `sink` is off.
+    pub fn new(
+        sink: AccessLogSink,
+        format: AccessLogFormat,
+        sample_rate: f64,
+        excluded_paths: Vec<String>,
+    ) -> std::io::Result<Self> {
+        let writer = match sink {
+            AccessLogSink::Off => None,
+            AccessLogSink::Stdout => Some(Writer::Stdout(tokio::io::stdout())),
+            AccessLogSink::File {
+                path,
+                max_bytes,
+                max_files,
+            } => Some(Writer::File(RotatingFile::open(
+                path, max_bytes, max_files,
+            )?)),
+        };
+        let output = writer.map(|mut writer| {
+            let (sender, mut receiver) = mpsc::channel::<String>(BUFFER_LINES);
+            tokio::spawn(async move {
+                while let Some(line) = receiver.recv().await {
+                    if let Err(e) = writer.write_line(&line).await {
+                        tracing::warn!(error = %e, "failed to write access log");
+                    }
+                }
+            });
+            sender
+        });
+
+        Ok(Self {
+            format,
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            excluded_paths,
+            output,
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    pub fn from_env() -> std::io::Result<Self> {
+        let sink = match ACCESS_LOG.as_str() {
+            "off" => AccessLogSink::Off,
+            "stdout" => AccessLogSink::Stdout,
+            path => AccessLogSink::File {
+                path: PathBuf::from(path),
+                max_bytes: *ACCESS_LOG_MAX_BYTES,
+                max_files: *ACCESS_LOG_MAX_FILES,
+            },
+        };
+        let format = match ACCESS_LOG_FORMAT.as_str() {
+            "json" => AccessLogFormat::Json,
+            "common" => AccessLogFormat::Common,
+            "combined" => AccessLogFormat::Combined,
+            other => panic!(
+                "ACCESS_LOG_FORMAT must be json, common or combined, got {}.",
+                other
+            ),
+        };
+        let excluded_paths = ACCESS_LOG_EXCLUDE_PATHS
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Self::new(sink, format, *ACCESS_LOG_SAMPLE_RATE, excluded_paths)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// Lines dropped so far because the sink fell behind.
+    pub fn dropped_lines(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Whether a request to `path` should be logged; excluded paths match by prefix.
+    pub fn should_log(&self, path: &str) -> bool {
+        if !self.is_enabled() || self.excluded_paths.iter().any(|p| path.starts_with(p)) {
+            return false;
+        }
+        self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate
+    }
+
+    pub fn log(&self, entry: &AccessLogEntry) {
+        let Some(sender) = &self.output else {
+            return;
+        };
+        if sender.try_send(entry.format(self.format)).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Append-only log file that rotates by size.
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            // shift path.1 → path.2 …, dropping the oldest
+            let _ = fs::remove_file(rotated(self.max_files));
+            for n in (1..self.max_files).rev() {
+                let _ = fs::rename(rotated(n), rotated(n + 1));
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn entry() -> AccessLogEntry {
+        AccessLogEntry {
+            timestamp: Utc.with_ymd_and_hms(2024, 10, 10, 13, 55, 36).unwrap(),
+            request_id: "5f0c6a1e".to_string(),
+            client_ip: Some("10.0.0.7".parse().unwrap()),
+            method: "GET".to_string(),
+            path: "/search?q=\"rust\"".to_string(),
+            protocol: "HTTP/1.1".to_string(),
+            status: 200,
+            bytes_in: 0,
+            bytes_out: 2326,
+            upstream: Some("http://localhost:7001/".to_string()),
+            upstream_latency_ms: Some(12.5),
+            total_latency_ms: 13.0,
+            retries: 1,
+            referer: None,
+            user_agent: Some("curl/8.0".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            entry().format(AccessLogFormat::Common),
+            r#"10.0.0.7 - - [10/Oct/2024:13:55:36 +0000] "GET /search?q=\"rust\" HTTP/1.1" 200 2326"#
+        );
+        assert_eq!(
+            entry().format(AccessLogFormat::Combined),
+            r#"10.0.0.7 - - [10/Oct/2024:13:55:36 +0000] "GET /search?q=\"rust\" HTTP/1.1" 200 2326 "-" "curl/8.0""#
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&entry().format(AccessLogFormat::Json)).unwrap();
+        assert_eq!(json["upstream"], "http://localhost:7001/");
+        assert_eq!(json["retries"], 1);
+        assert_eq!(json["request_id"], "5f0c6a1e");
+        assert_eq!(json["upstream_latency_ms"], 12.5);
+    }
+
+    #[tokio::test]
+    async fn test_exclusions_and_sampling() {
+        let logger = AccessLogger::new(
+            AccessLogSink::Stdout,
+            AccessLogFormat::Json,
+            1.0,
+            vec!["/healthz".to_string()],
+        )
+        .unwrap();
+        assert!(logger.should_log("/api"));
+        assert!(!logger.should_log("/healthz"));
+
+        let never =
+            AccessLogger::new(AccessLogSink::Stdout, AccessLogFormat::Json, 0.0, vec![]).unwrap();
+        assert!(!never.should_log("/api"));
+        assert!(!AccessLogger::disabled().should_log("/api"));
+    }
+
+    #[tokio::test]
+    async fn test_full_buffer_drops_lines() {
+        let dir = std::env::temp_dir().join(format!("access-log-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("access.log");
+        let logger = AccessLogger::new(
+            AccessLogSink::File {
+                path: path.clone(),
+                max_bytes: u64::MAX,
+                max_files: 1,
+            },
+            AccessLogFormat::Common,
+            1.0,
+            vec![],
+        )
+        .unwrap();
+
+        // the writer task cannot run before this test yields
+        for _ in 0..BUFFER_LINES + 3 {
+            logger.log(&entry());
+        }
+        assert_eq!(logger.dropped_lines(), 3);
+
+        drop(logger);
+        for _ in 0..100 {
+            tokio::task::yield_now().await;
+        }
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(written.lines().count(), BUFFER_LINES);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("access-log-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("access.log");
+
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        for line in ["first", "second", "third", "fourth"] {
+            file.write_line(line).unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("access.log"), "fourth\n");
+        assert_eq!(read("access.log.1"), "third\n");
+        assert_eq!(read("access.log.2"), "second\n");
+        assert!(!dir.join("access.log.3").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,573 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use bollard::{
+    container::{
+        Config, CreateContainerOptions, ListContainersOptions, RemoveContainerOptions,
+        StopContainerOptions,
+    },
+    secret::{ContainerSummary, HostConfig, PortBinding},
+    Docker,
+};
+
+use crate::{
+    app_state::{DockerStatsCache, EndpointStoreType},
+    domain::{ContainerRef, Endpoint},
+    services::discovery::docker::{POOL_LABEL, PORT_LABEL},
+    utils::constants::{
+        AUTOSCALE_COOLDOWN_SECS, AUTOSCALE_DRAIN_TIMEOUT_SECS, AUTOSCALE_DRY_RUN, AUTOSCALE_IMAGE,
+        AUTOSCALE_INTERVAL_SECS, AUTOSCALE_MAX_REPLICAS, AUTOSCALE_MIN_REPLICAS, AUTOSCALE_NETWORK,
+        AUTOSCALE_PORT, AUTOSCALE_SUSTAIN_SECS, AUTOSCALE_TARGET_CONNECTIONS, AUTOSCALE_TARGET_CPU,
+    },
+};
+
+/// Marks containers the autoscaler started; only these are ever stopped by it.
+pub const AUTOSCALED_LABEL: &str = "roundest-robin.autoscaled";
+
+#[derive(Debug, Clone)]
+pub struct AutoscaleSettings {
+    /// Docker discovery pool to manage; new containers join it through its labels.
+    pub pool: String,
+    pub image: String,
+    /// Container port the image serves on.
+    pub port: u16,
+    /// Docker network to attach new containers to; the default bridge when unset.
+    pub network: Option<String>,
+    pub min_replicas: usize,
+    pub max_replicas: usize,
+    /// Average in-flight requests per replica to stay under.
+    pub target_connections: f64,
+    /// Average container CPU % to stay under.
+    pub target_cpu: f64,
+    /// How long load must stay over (or well under) target before acting.
+    pub sustain: Duration,
+    /// Minimum time between two scaling actions.
+    pub cooldown: Duration,
+    pub drain_timeout: Duration,
+    pub interval: Duration,
+    /// Log decisions without touching any container.
+    pub dry_run: bool,
+}
+
+impl AutoscaleSettings {
+    pub fn from_env(pool: &str) -> Self {
+        assert!(
+            *AUTOSCALE_MIN_REPLICAS <= *AUTOSCALE_MAX_REPLICAS,
+            "AUTOSCALE_MIN_REPLICAS must not be above AUTOSCALE_MAX_REPLICAS."
+        );
+        Self {
+            pool: pool.to_string(),
+            image: AUTOSCALE_IMAGE
+                .clone()
+                .expect("AUTOSCALE_IMAGE must be set to autoscale a pool."),
+            port: *AUTOSCALE_PORT,
+            network: AUTOSCALE_NETWORK.clone(),
+            min_replicas: *AUTOSCALE_MIN_REPLICAS,
+            max_replicas: *AUTOSCALE_MAX_REPLICAS,
+            target_connections: *AUTOSCALE_TARGET_CONNECTIONS,
+            target_cpu: *AUTOSCALE_TARGET_CPU,
+            sustain: Duration::from_secs(*AUTOSCALE_SUSTAIN_SECS),
+            cooldown: Duration::from_secs(*AUTOSCALE_COOLDOWN_SECS),
+            drain_timeout: Duration::from_secs(*AUTOSCALE_DRAIN_TIMEOUT_SECS),
+            interval: Duration::from_secs(*AUTOSCALE_INTERVAL_SECS),
+            dry_run: *AUTOSCALE_DRY_RUN,
+        }
+    }
+}
+
+/// Load across a pool at one point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolLoad {
+    pub replicas: usize,
+    pub avg_connections: f64,
+    /// `None` when no replica has a stats sample yet.
+    pub avg_cpu: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScaleDecision {
+    Hold,
+    Up,
+    Down,
+}
+
+/// Turns a series of load readings into scaling decisions.
+///
+/// The pool scales up once connections or CPU have been over target for `sustain`, and
+/// down once both have stayed under half the target for as long. Replica counts outside
+/// `[min, max]` are corrected without waiting for `sustain`. Every action waits out the
+/// cooldown, so a replica that is not listed yet, or one a dry run never started, is not
+/// asked for again on every tick.
+pub struct ScalingPolicy {
+    settings: AutoscaleSettings,
+    over_since: Option<Instant>,
+    under_since: Option<Instant>,
+    last_scaled: Option<Instant>,
+}
+
+impl ScalingPolicy {
+    pub fn new(settings: AutoscaleSettings) -> Self {
+        Self {
+            settings,
+            over_since: None,
+            under_since: None,
+            last_scaled: None,
+        }
+    }
+
+    pub fn decide(&mut self, load: &PoolLoad, now: Instant) -> ScaleDecision {
+        let settings = &self.settings;
+
+        let cpu = load.avg_cpu.unwrap_or(0.0);
+        let over = load.avg_connections > settings.target_connections || cpu > settings.target_cpu;
+        let under = load.avg_connections < settings.target_connections / 2.0
+            && cpu < settings.target_cpu / 2.0;
+
+        self.over_since = if over {
+            self.over_since.or(Some(now))
+        } else {
+            None
+        };
+        self.under_since = if under {
+            self.under_since.or(Some(now))
+        } else {
+            None
+        };
+
+        let decision = if self
+            .last_scaled
+            .is_some_and(|last| now.duration_since(last) < settings.cooldown)
+        {
+            ScaleDecision::Hold
+        } else if load.replicas < settings.min_replicas {
+            ScaleDecision::Up
+        } else if load.replicas > settings.max_replicas {
+            ScaleDecision::Down
+        } else if sustained(self.over_since, now, settings.sustain)
+            && load.replicas < settings.max_replicas
+        {
+            ScaleDecision::Up
+        } else if sustained(self.under_since, now, settings.sustain)
+            && load.replicas > settings.min_replicas
+        {
+            ScaleDecision::Down
+        } else {
+            ScaleDecision::Hold
+        };
+
+        if decision != ScaleDecision::Hold {
+            self.last_scaled = Some(now);
+            self.over_since = None;
+            self.under_since = None;
+        }
+        decision
+    }
+}
+
+fn sustained(since: Option<Instant>, now: Instant, period: Duration) -> bool {
+    since.is_some_and(|since| now.duration_since(since) >= period)
+}
+
+/// Starts and stops containers of a Docker discovery pool to follow its load.
+pub struct Autoscaler {
+    docker: Docker,
+    store: EndpointStoreType,
+    stats: DockerStatsCache,
+    policy: ScalingPolicy,
+}
+
+impl Autoscaler {
+    pub fn new(
+        docker: Docker,
+        settings: AutoscaleSettings,
+        store: EndpointStoreType,
+        stats: DockerStatsCache,
+    ) -> Self {
+        Self {
+            docker,
+            store,
+            stats,
+            policy: ScalingPolicy::new(settings),
+        }
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.policy.settings.interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.tick().await {
+                tracing::warn!(error = %e, "autoscaler tick failed");
+            }
+        }
+    }
+
+    async fn tick(&mut self) -> Result<(), bollard::errors::Error> {
+        let containers = self.pool_containers().await?;
+        let replicas = self.replicas(&containers).await;
+        let load = pool_load(&replicas);
+
+        let decision = self.policy.decide(&load, Instant::now());
+        let settings = &self.policy.settings;
+        let target = match decision {
+            ScaleDecision::Hold => return Ok(()),
+            ScaleDecision::Up => load.replicas + 1,
+            ScaleDecision::Down => load.replicas.saturating_sub(1),
+        };
+        tracing::info!(
+            pool = %settings.pool,
+            from = load.replicas,
+            to = target,
+            avg_connections = load.avg_connections,
+            avg_cpu = ?load.avg_cpu,
+            dry_run = settings.dry_run,
+            "scaling pool"
+        );
+        if settings.dry_run {
+            return Ok(());
+        }
+
+        match decision {
+            ScaleDecision::Up => self.start_replica().await,
+            ScaleDecision::Down => self.stop_replica(&containers, &replicas).await,
+            ScaleDecision::Hold => Ok(()),
+        }
+    }
+
+    async fn pool_containers(&self) -> Result<Vec<ContainerSummary>, bollard::errors::Error> {
+        self.docker
+            .list_containers(Some(ListContainersOptions::<String> {
+                all: false, // running only
+                filters: HashMap::from([(
+                    "label".to_string(),
+                    vec![format!("{}={}", POOL_LABEL, self.policy.settings.pool)],
+                )]),
+                ..Default::default()
+            }))
+            .await
+    }
+
+    /// Each running pool container with the endpoints it serves and its latest CPU sample.
+    async fn replicas(&self, containers: &[ContainerSummary]) -> Vec<Replica> {
+        let endpoints = self
+            .store
+            .read()
+            .await
+            .get_all_endpoints()
+            .await
+            .unwrap_or_default();
+        let snapshot = self.stats.read().await;
+
+        containers
+            .iter()
+            .filter_map(ContainerRef::from_summary)
+            .map(|container| {
+                let endpoints = endpoints
+                    .iter()
+                    .filter(|ep| {
+                        snapshot
+                            .index
+                            .find(&ep.uri)
+                            .is_some_and(|found| found.id == container.id)
+                    })
+                    .cloned()
+                    .collect();
+                let cpu = snapshot
+                    .stats
+                    .get(&container.id)
+                    .map(|stats| stats.cpu_percentage);
+                Replica {
+                    container,
+                    endpoints,
+                    cpu,
+                }
+            })
+            .collect()
+    }
+
+    async fn start_replica(&self) -> Result<(), bollard::errors::Error> {
+        let settings = &self.policy.settings;
+        let port = format!("{}/tcp", settings.port);
+        let name = format!(
+            "{}-{}",
+            settings.pool,
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+
+        let config = Config {
+            image: Some(settings.image.clone()),
+            labels: Some(HashMap::from([
+                (POOL_LABEL.to_string(), settings.pool.clone()),
+                (PORT_LABEL.to_string(), settings.port.to_string()),
+                (AUTOSCALED_LABEL.to_string(), "true".to_string()),
+            ])),
+            exposed_ports: Some(HashMap::from([(port.clone(), HashMap::new())])),
+            host_config: Some(HostConfig {
+                // an empty host port lets Docker pick a free one
+                port_bindings: Some(HashMap::from([(
+                    port,
+                    Some(vec![PortBinding {
+                        host_ip: None,
+                        host_port: Some(String::new()),
+                    }]),
+                )])),
+                network_mode: settings.network.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        self.docker
+            .create_container(
+                Some(CreateContainerOptions {
+                    name: name.clone(),
+                    platform: None,
+                }),
+                config,
+            )
+            .await?;
+        self.docker.start_container::<String>(&name, None).await?;
+        tracing::info!(container = %name, "started replica");
+        Ok(())
+    }
+
+    /// Drain the least busy container we started, then stop and remove it.
+    async fn stop_replica(
+        &self,
+        containers: &[ContainerSummary],
+        replicas: &[Replica],
+    ) -> Result<(), bollard::errors::Error> {
+        let Some(replica) = replicas
+            .iter()
+            .filter(|replica| {
+                containers.iter().any(|container| {
+                    container.id.as_ref() == Some(&replica.container.id)
+                        && container
+                            .labels
+                            .as_ref()
+                            .is_some_and(|labels| labels.contains_key(AUTOSCALED_LABEL))
+                })
+            })
+            .min_by_key(|replica| replica.connections())
+        else {
+            tracing::info!(
+                pool = %self.policy.settings.pool,
+                "no autoscaled container left to stop"
+            );
+            return Ok(());
+        };
+
+        for endpoint in &replica.endpoints {
+            endpoint.drain();
+        }
+        let deadline = Instant::now() + self.policy.settings.drain_timeout;
+        while replica.connections() > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+
+        let id = &replica.container.id;
+        self.docker
+            .stop_container(id, Some(StopContainerOptions { t: 10 }))
+            .await?;
+        self.docker
+            .remove_container(
+                id,
+                Some(RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                }),
+            )
+            .await?;
+        tracing::info!(container = %replica.container.name, "stopped replica");
+        Ok(())
+    }
+}
+
+struct Replica {
+    container: ContainerRef,
+    endpoints: Vec<Endpoint>,
+    cpu: Option<f64>,
+}
+
+impl Replica {
+    fn connections(&self) -> usize {
+        self.endpoints
+            .iter()
+            .map(Endpoint::concurrent_connection_count)
+            .sum()
+    }
+}
+
+fn pool_load(replicas: &[Replica]) -> PoolLoad {
+    let count = replicas.len();
+    let connections: usize = replicas.iter().map(Replica::connections).sum();
+    let cpus: Vec<f64> = replicas.iter().filter_map(|replica| replica.cpu).collect();
+
+    PoolLoad {
+        replicas: count,
+        avg_connections: if count == 0 {
+            0.0
+        } else {
+            connections as f64 / count as f64
+        },
+        avg_cpu: if cpus.is_empty() {
+            None
+        } else {
+            Some(cpus.iter().sum::<f64>() / cpus.len() as f64)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> AutoscaleSettings {
+        AutoscaleSettings {
+            pool: "web".to_string(),
+            image: "web:latest".to_string(),
+            port: 8080,
+            network: None,
+            min_replicas: 1,
+            max_replicas: 3,
+            target_connections: 10.0,
+            target_cpu: 70.0,
+            sustain: Duration::from_secs(30),
+            cooldown: Duration::from_secs(60),
+            drain_timeout: Duration::from_secs(30),
+            interval: Duration::from_secs(5),
+            dry_run: true,
+        }
+    }
+
+    fn load(replicas: usize, avg_connections: f64, avg_cpu: Option<f64>) -> PoolLoad {
+        PoolLoad {
+            replicas,
+            avg_connections,
+            avg_cpu,
+        }
+    }
+
+    #[test]
+    fn test_scales_up_after_sustained_load_then_cools_down() {
+        let mut policy = ScalingPolicy::new(settings());
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(
+            policy.decide(&load(1, 15.0, None), at(0)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(1, 15.0, None), at(20)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(1, 15.0, None), at(30)),
+            ScaleDecision::Up
+        );
+
+        // still overloaded, but within the cooldown
+        assert_eq!(
+            policy.decide(&load(2, 12.0, None), at(40)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(2, 12.0, None), at(89)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(2, 12.0, None), at(90)),
+            ScaleDecision::Up
+        );
+
+        // capped at max_replicas
+        assert_eq!(
+            policy.decide(&load(3, 50.0, None), at(200)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(3, 50.0, None), at(300)),
+            ScaleDecision::Hold
+        );
+    }
+
+    #[test]
+    fn test_cpu_alone_triggers_scale_up() {
+        let mut policy = ScalingPolicy::new(settings());
+        let start = Instant::now();
+
+        policy.decide(&load(1, 0.0, Some(90.0)), start);
+        assert_eq!(
+            policy.decide(&load(1, 0.0, Some(90.0)), start + Duration::from_secs(30)),
+            ScaleDecision::Up
+        );
+    }
+
+    #[test]
+    fn test_scales_down_when_idle_but_not_below_min() {
+        let mut policy = ScalingPolicy::new(settings());
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // a blip of load resets the idle timer
+        assert_eq!(
+            policy.decide(&load(2, 1.0, Some(5.0)), at(0)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(2, 6.0, Some(5.0)), at(20)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(2, 1.0, Some(5.0)), at(25)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(2, 1.0, Some(5.0)), at(55)),
+            ScaleDecision::Down
+        );
+
+        assert_eq!(
+            policy.decide(&load(1, 0.0, None), at(200)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(1, 0.0, None), at(300)),
+            ScaleDecision::Hold
+        );
+    }
+
+    #[test]
+    fn test_replica_bounds_skip_sustain_but_not_cooldown() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let mut policy = ScalingPolicy::new(settings());
+        assert_eq!(policy.decide(&load(0, 0.0, None), at(0)), ScaleDecision::Up);
+        // the new replica is not listed yet
+        assert_eq!(
+            policy.decide(&load(0, 0.0, None), at(5)),
+            ScaleDecision::Hold
+        );
+        assert_eq!(
+            policy.decide(&load(0, 0.0, None), at(60)),
+            ScaleDecision::Up
+        );
+
+        let mut policy = ScalingPolicy::new(settings());
+        assert_eq!(
+            policy.decide(&load(5, 0.0, None), at(0)),
+            ScaleDecision::Down
+        );
+        assert_eq!(
+            policy.decide(&load(5, 0.0, None), at(5)),
+            ScaleDecision::Hold
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    domain::{Endpoint, ResourceSample},
+    utils::constants::{
+        BALANCING_STRATEGY, RESOURCE_CPU_HARD_LIMIT, RESOURCE_CPU_SOFT_LIMIT,
+        RESOURCE_MEMORY_HARD_LIMIT, RESOURCE_MEMORY_SOFT_LIMIT, RESOURCE_STATS_MAX_AGE_SECS,
+    },
+};
+
+/// Resolution of the load penalty: a healthy endpoint's weight is multiplied by this,
+/// so a loaded one can be scaled down in steps of a tenth.
+const WEIGHT_SCALE: usize = 10;
+
+/// How the next endpoint is picked from the active ones.
+#[derive(Debug, Clone, Default)]
+pub enum BalancingStrategy {
+    /// Weighted round robin.
+    #[default]
+    RoundRobin,
+    /// Weighted round robin, with weights scaled down by container CPU and memory usage.
+    ResourceAware(ResourceThresholds),
+}
+
+/// CPU and memory limits, in percent, for resource-aware balancing.
+///
+/// Between the soft and hard limit an endpoint's share shrinks linearly; at or above the
+/// hard limit it gets no traffic. Samples older than `max_age` are ignored.
+#[derive(Debug, Clone)]
+pub struct ResourceThresholds {
+    pub cpu_soft: f64,
+    pub cpu_hard: f64,
+    pub memory_soft: f64,
+    pub memory_hard: f64,
+    pub max_age: Duration,
+}
+
+impl BalancingStrategy {
+    pub fn from_env() -> Self {
+        Self::parse(&BALANCING_STRATEGY).unwrap_or_else(|| {
+            panic!(
+                "BALANCING_STRATEGY must be round_robin or resource_aware, got {}.",
+                *BALANCING_STRATEGY
+            )
+        })
+    }
+
+    /// Strategy by name; resource-aware thresholds always come from the environment.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "round_robin" => Some(BalancingStrategy::RoundRobin),
+            "resource_aware" => Some(BalancingStrategy::ResourceAware(ResourceThresholds {
+                cpu_soft: *RESOURCE_CPU_SOFT_LIMIT,
+                cpu_hard: *RESOURCE_CPU_HARD_LIMIT,
+                memory_soft: *RESOURCE_MEMORY_SOFT_LIMIT,
+                memory_hard: *RESOURCE_MEMORY_HARD_LIMIT,
+                max_age: Duration::from_secs(*RESOURCE_STATS_MAX_AGE_SECS),
+            })),
+            _ => None,
+        }
+    }
+
+    /// Selection weight of each endpoint, in order; 0 means skip it.
+    ///
+    /// Falls back to the configured weights when no endpoint has a fresh sample, or when
+    /// every endpoint is over its hard limit, since serving slowly beats not serving.
+    pub fn weights(&self, endpoints: &[&Endpoint], now: Instant) -> Vec<usize> {
+        let configured = || endpoints.iter().map(|ep| ep.weight()).collect();
+
+        let BalancingStrategy::ResourceAware(thresholds) = self else {
+            return configured();
+        };
+
+        let samples: Vec<Option<ResourceSample>> = endpoints
+            .iter()
+            .map(|ep| {
+                ep.resources()
+                    .filter(|sample| now.duration_since(sample.sampled_at) <= thresholds.max_age)
+            })
+            .collect();
+        if samples.iter().all(Option::is_none) {
+            return configured();
+        }
+
+        let weights: Vec<usize> = endpoints
+            .iter()
+            .zip(samples)
+            .map(|(ep, sample)| match sample {
+                Some(sample) => thresholds.scaled_weight(ep.weight(), &sample),
+                None => ep.weight() * WEIGHT_SCALE,
+            })
+            .collect();
+        if weights.iter().all(|weight| *weight == 0) {
+            return configured();
+        }
+        weights
+    }
+}
+
+impl ResourceThresholds {
+    fn scaled_weight(&self, weight: usize, sample: &ResourceSample) -> usize {
+        if sample.cpu_percentage >= self.cpu_hard || sample.memory_percentage >= self.memory_hard {
+            return 0;
+        }
+
+        let load = overload(sample.cpu_percentage, self.cpu_soft, self.cpu_hard).max(overload(
+            sample.memory_percentage,
+            self.memory_soft,
+            self.memory_hard,
+        ));
+        let scaled = (weight * WEIGHT_SCALE) as f64 * (1.0 - load);
+        (scaled.ceil() as usize).max(1)
+    }
+}
+
+/// How far `usage` is from the soft limit towards the hard one, from 0 to 1.
+fn overload(usage: f64, soft: f64, hard: f64) -> f64 {
+    if usage <= soft || hard <= soft {
+        return 0.0;
+    }
+    ((usage - soft) / (hard - soft)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Uri;
+
+    use super::*;
+
+    fn thresholds() -> BalancingStrategy {
+        BalancingStrategy::ResourceAware(ResourceThresholds {
+            cpu_soft: 50.0,
+            cpu_hard: 90.0,
+            memory_soft: 70.0,
+            memory_hard: 90.0,
+            max_age: Duration::from_secs(10),
+        })
+    }
+
+    fn endpoint(uri: &'static str, cpu: f64, memory: f64, sampled_at: Instant) -> Endpoint {
+        let endpoint = Endpoint::new(Uri::from_static(uri));
+        endpoint.record_resources(ResourceSample {
+            cpu_percentage: cpu,
+            memory_percentage: memory,
+            sampled_at,
+        });
+        endpoint
+    }
+
+    #[test]
+    fn test_resource_aware_weights() {
+        let now = Instant::now();
+        let idle = endpoint("http://idle:80", 10.0, 20.0, now);
+        let busy = endpoint("http://busy:80", 70.0, 20.0, now);
+        let swapping = endpoint("http://swapping:80", 10.0, 95.0, now);
+        let unknown = Endpoint::new(Uri::from_static("http://unknown:80"));
+
+        let weights = thresholds().weights(&[&idle, &busy, &swapping, &unknown], now);
+
+        // busy is halfway between its CPU limits; unknown is treated as healthy
+        assert_eq!(weights, vec![10, 5, 0, 10]);
+    }
+
+    #[test]
+    fn test_stale_or_overloaded_falls_back_to_configured_weights() {
+        let now = Instant::now();
+        let stale = now - Duration::from_secs(60);
+        let a = endpoint("http://a:80", 99.0, 20.0, stale).with_weight(2);
+        let b = endpoint("http://b:80", 10.0, 99.0, stale);
+
+        assert_eq!(thresholds().weights(&[&a, &b], now), vec![2, 1]);
+
+        a.record_resources(ResourceSample {
+            cpu_percentage: 99.0,
+            memory_percentage: 20.0,
+            sampled_at: now,
+        });
+        b.record_resources(ResourceSample {
+            cpu_percentage: 10.0,
+            memory_percentage: 99.0,
+            sampled_at: now,
+        });
+        assert_eq!(thresholds().weights(&[&a, &b], now), vec![2, 1]);
+    }
+}
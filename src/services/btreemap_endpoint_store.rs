@@ -0,0 +1,325 @@
+use crate::domain::{Endpoint, EndpointStore, EndpointStoreError, HealthCheckResult};
+use crate::services::balancing::BalancingStrategy;
+use axum::http::Uri;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Endpoints are kept sorted by URI so round-robin selection is predictable.
+#[derive(Default)]
+pub struct BTreeMapEndpointStore {
+    endpoints: BTreeMap<String, Endpoint>,
+    current_index: AtomicUsize,
+    strategy: BalancingStrategy,
+}
+
+#[async_trait::async_trait]
+impl EndpointStore for BTreeMapEndpointStore {
+    async fn add_endpoint(&mut self, endpoint: Endpoint) -> Result<(), EndpointStoreError> {
+        let key = endpoint.uri.to_string();
+        if self.endpoints.contains_key(&key) {
+            return Err(EndpointStoreError::EndpointAlreadyExists);
+        }
+        self.endpoints.insert(key, endpoint);
+        Ok(())
+    }
+
+    async fn remove_endpoint(&mut self, uri: &Uri) -> Result<Endpoint, EndpointStoreError> {
+        self.endpoints
+            .remove(&uri.to_string())
+            .ok_or(EndpointStoreError::EndpointNotFound)
+    }
+
+    async fn get_all_endpoints(&self) -> Result<Vec<Endpoint>, EndpointStoreError> {
+        Ok(self.endpoints.values().cloned().collect())
+    }
+
+    async fn get_next_endpoint_excluding(
+        &self,
+        excluded: &[Uri],
+    ) -> Result<Endpoint, EndpointStoreError> {
+        // filter for active servers
+        let active_endpoints: Vec<_> = self
+            .endpoints
+            .values()
+            .filter(|ep| ep.active_server.load(Ordering::Relaxed) && !ep.is_draining())
+            .filter(|ep| !excluded.contains(&ep.uri))
+            .collect();
+
+        if active_endpoints.is_empty() {
+            return Err(EndpointStoreError::NoEndpoints);
+        }
+
+        let weights = self.strategy.weights(&active_endpoints, Instant::now());
+        let selected_endpoint = self.round_robin_index_selection(active_endpoints, weights);
+
+        Ok(selected_endpoint.clone())
+    }
+
+    async fn check_for_dead_servers(&self) -> Vec<HealthCheckResult> {
+        // only check once the seconds on the clock are a multiple of 5
+        // let current_time = tokio::time::Instant::now();
+        // if !current_time.elapsed().as_secs().is_multiple_of(5) {
+        //     return;
+        // }
+
+        // iterate through endpoints whose are still active
+        let mut results = Vec::new();
+
+        for endpoint in self.endpoints.values() {
+            if !endpoint.active_server.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            // Simple health check logic: if failure count exceeds success count by a threshold, deactivate
+            let success_count = endpoint.success_count();
+            let failure_count = endpoint.failure_count();
+
+            // if ratio of failures to successes exceeds 10%, deactivate
+            let healthy = !(success_count > 0 && failure_count > success_count / 10);
+            if !healthy {
+                // in-flight requests still decrement the connection count when they finish
+                endpoint.deactivate();
+                tracing::warn!(endpoint = %endpoint.uri, success_count, failure_count, "deactivated endpoint");
+            }
+            results.push(HealthCheckResult {
+                endpoint: endpoint.clone(),
+                healthy,
+            });
+        }
+        results
+    }
+}
+
+impl BTreeMapEndpointStore {
+    pub fn with_strategy(self, strategy: BalancingStrategy) -> Self {
+        Self { strategy, ..self }
+    }
+
+    /// Weighted round robin: each endpoint gets `weight` consecutive turns per cycle.
+    fn round_robin_index_selection(
+        &self,
+        active_endpoints: Vec<&Endpoint>,
+        weights: Vec<usize>,
+    ) -> Endpoint {
+        let total_weight: usize = weights.iter().sum();
+
+        if self.current_index.load(Ordering::Relaxed) >= total_weight {
+            self.current_index.store(0, Ordering::Relaxed);
+        }
+
+        let mut slot = self.current_index.fetch_add(1, Ordering::Relaxed) % total_weight;
+        for (endpoint, weight) in active_endpoints.iter().zip(weights) {
+            if slot < weight {
+                return (*endpoint).clone();
+            }
+            slot -= weight;
+        }
+        active_endpoints[0].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::AtomicBool, Arc},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{domain::ResourceSample, services::balancing::ResourceThresholds};
+
+    #[tokio::test]
+    async fn test_add_endpoint() {
+        let mut endpoint_store = BTreeMapEndpointStore::default();
+
+        let endpoint1 = Endpoint::new(Uri::from_static("http://example.com"));
+        let endpoint2 = Endpoint::new(Uri::from_static("http://example-two.com"));
+
+        // Test adding a new user
+        let result = endpoint_store.add_endpoint(endpoint1).await;
+        assert!(result.is_ok());
+
+        // Test adding an existing user
+        let result = endpoint_store.add_endpoint(endpoint2).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_remove_endpoint() {
+        let mut endpoint_store = BTreeMapEndpointStore::default();
+        let uri = Uri::from_static("http://example.com");
+
+        let result = endpoint_store.remove_endpoint(&uri).await;
+        assert_eq!(result.unwrap_err(), EndpointStoreError::EndpointNotFound);
+
+        let _ = endpoint_store
+            .add_endpoint(Endpoint::new(uri.clone()))
+            .await;
+        let removed = endpoint_store.remove_endpoint(&uri).await.unwrap();
+        assert_eq!(removed.uri, uri);
+        assert!(endpoint_store.get_next_endpoint().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_endpoint() {
+        let mut endpoint_store = BTreeMapEndpointStore::default();
+        let endpoint1 = Endpoint::new(Uri::from_static("http://example.com"));
+        let endpoint2 = Endpoint::new(Uri::from_static("http://example-two.com"));
+
+        // Test getting endpoint from empty store
+        let result = endpoint_store.get_next_endpoint().await;
+        assert!(result.is_err());
+
+        // Add endpoints
+        let _ = endpoint_store.add_endpoint(endpoint1).await;
+        let _ = endpoint_store.add_endpoint(endpoint2).await;
+
+        // Test that we get different endpoints on successive calls - round robin 1, 0, 1
+        let first_endpoint = endpoint_store.get_next_endpoint().await.unwrap();
+        let second_endpoint = endpoint_store.get_next_endpoint().await.unwrap();
+        let third_endpoint = endpoint_store.get_next_endpoint().await.unwrap();
+
+        print!(
+            "First: {:?}, Second: {:?}, Third: {:?}",
+            first_endpoint, second_endpoint, third_endpoint
+        );
+
+        assert_eq!(
+            first_endpoint.uri,
+            Uri::from_static("http://example-two.com")
+        );
+        assert_eq!(second_endpoint.uri, Uri::from_static("http://example.com"));
+        assert_eq!(
+            third_endpoint.uri,
+            Uri::from_static("http://example-two.com")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_endpoint_weighted() {
+        let mut endpoint_store = BTreeMapEndpointStore::default();
+        let heavy = Endpoint::new(Uri::from_static("http://heavy.com")).with_weight(3);
+        let light = Endpoint::new(Uri::from_static("http://light.com"));
+
+        let _ = endpoint_store.add_endpoint(heavy).await;
+        let _ = endpoint_store.add_endpoint(light).await;
+
+        let mut heavy_count = 0;
+        for _ in 0..8 {
+            let endpoint = endpoint_store.get_next_endpoint().await.unwrap();
+            if endpoint.uri == Uri::from_static("http://heavy.com") {
+                heavy_count += 1;
+            }
+        }
+        assert_eq!(heavy_count, 6);
+    }
+
+    #[tokio::test]
+    async fn test_get_endpoint_excluding_moves_past_a_heavy_endpoint() {
+        let mut endpoint_store = BTreeMapEndpointStore::default();
+        let dead = Uri::from_static("http://dead.com");
+        let healthy = Uri::from_static("http://healthy.com");
+        let _ = endpoint_store
+            .add_endpoint(Endpoint::new(dead.clone()).with_weight(3))
+            .await;
+        let _ = endpoint_store
+            .add_endpoint(Endpoint::new(healthy.clone()))
+            .await;
+
+        // the first pick fails; plain round robin would give it two more turns
+        assert_eq!(endpoint_store.get_next_endpoint().await.unwrap().uri, dead);
+        let tried = [dead.clone()];
+        for _ in 0..3 {
+            let retry = endpoint_store.get_next_endpoint_excluding(&tried).await;
+            assert_eq!(retry.unwrap().uri, healthy);
+        }
+        assert_eq!(
+            endpoint_store
+                .get_next_endpoint_excluding(&[dead, healthy])
+                .await
+                .unwrap_err(),
+            EndpointStoreError::NoEndpoints
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_endpoint_skips_draining() {
+        let mut endpoint_store = BTreeMapEndpointStore::default();
+        let draining = Endpoint::new(Uri::from_static("http://example.com"));
+        draining.drain();
+        let _ = endpoint_store.add_endpoint(draining).await;
+        let _ = endpoint_store
+            .add_endpoint(Endpoint::new(Uri::from_static("http://example-two.com")))
+            .await;
+
+        for _ in 0..3 {
+            let endpoint = endpoint_store.get_next_endpoint().await.unwrap();
+            assert_eq!(endpoint.uri, Uri::from_static("http://example-two.com"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_endpoint_resource_aware_avoids_overloaded() {
+        let mut endpoint_store = BTreeMapEndpointStore::default().with_strategy(
+            BalancingStrategy::ResourceAware(ResourceThresholds {
+                cpu_soft: 70.0,
+                cpu_hard: 95.0,
+                memory_soft: 80.0,
+                memory_hard: 95.0,
+                max_age: Duration::from_secs(10),
+            }),
+        );
+        let overloaded = Endpoint::new(Uri::from_static("http://example.com"));
+        overloaded.record_resources(ResourceSample {
+            cpu_percentage: 99.0,
+            memory_percentage: 10.0,
+            sampled_at: Instant::now(),
+        });
+        let _ = endpoint_store.add_endpoint(overloaded).await;
+        let _ = endpoint_store
+            .add_endpoint(Endpoint::new(Uri::from_static("http://example-two.com")))
+            .await;
+
+        for _ in 0..5 {
+            let endpoint = endpoint_store.get_next_endpoint().await.unwrap();
+            assert_eq!(endpoint.uri, Uri::from_static("http://example-two.com"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_endpoint_failed_server() {
+        let mut endpoint_store = BTreeMapEndpointStore::default();
+        let endpoint1 = Endpoint {
+            uri: Uri::from_static("http://example.com"),
+            count_success: Default::default(),
+            count_failure: Default::default(),
+            count_concurrent_connections: Default::default(),
+            active_server: Arc::new(AtomicBool::new(false)), // inactive server
+            weight: Arc::new(AtomicUsize::new(1)),
+            draining: Default::default(),
+            resources: Default::default(),
+            windows: Default::default(),
+        };
+
+        let endpoint2 = Endpoint {
+            uri: Uri::from_static("http://example-two.com"),
+            count_success: Default::default(),
+            count_failure: Default::default(),
+            count_concurrent_connections: Default::default(),
+            active_server: Arc::new(AtomicBool::new(false)), // inactive server
+            weight: Arc::new(AtomicUsize::new(1)),
+            draining: Default::default(),
+            resources: Default::default(),
+            windows: Default::default(),
+        };
+
+        // Add endpoint
+        let _ = endpoint_store.add_endpoint(endpoint1).await;
+        let _ = endpoint_store.add_endpoint(endpoint2).await;
+
+        // Test that we get error when all servers are inactive
+        let result = endpoint_store.get_next_endpoint().await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use std::time::{Duration, Instant};
+
+use hickory_resolver::{
+    config::{LookupIpStrategy, ResolverConfig, ResolverOpts},
+    error::{ResolveError, ResolveErrorKind},
+    TokioAsyncResolver,
+};
+
+use super::{format_host_port, DiscoveredEndpoint, Reconciler};
+use crate::app_state::EndpointStoreType;
+
+/// Never re-resolve more often than this, however short the record TTLs are.
+pub const MIN_REFRESH: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum DnsQuery {
+    /// SRV record such as `_http._tcp.backend.internal`; ports and weights come from the records.
+    Srv { name: String },
+    /// A/AAAA records of `name`, every address served on `port`.
+    Host { name: String, port: u16 },
+}
+
+/// Discovers backends by re-resolving a DNS name.
+///
+/// The name is resolved again when its records expire, or after `interval` if that
+/// comes first. Lookup failures leave the current endpoints untouched; only an
+/// answer with no records empties the set.
+pub struct DnsDiscovery {
+    resolver: TokioAsyncResolver,
+    query: DnsQuery,
+    scheme: String,
+    interval: Duration,
+    reconciler: Reconciler,
+}
+
+impl DnsDiscovery {
+    pub fn new(
+        resolver: TokioAsyncResolver,
+        query: DnsQuery,
+        scheme: &str,
+        interval: Duration,
+        store: EndpointStoreType,
+    ) -> Self {
+        Self {
+            resolver,
+            query,
+            scheme: scheme.to_string(),
+            interval,
+            reconciler: Reconciler::new("dns", store),
+        }
+    }
+
+    /// Resolver that returns both A and AAAA records, so dual-stack backends get an endpoint per address.
+    pub fn resolver(config: ResolverConfig, mut options: ResolverOpts) -> TokioAsyncResolver {
+        options.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+        TokioAsyncResolver::tokio(config, options)
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        loop {
+            let delay = self.refresh().await;
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Resolve once, reconcile the store and return how long to wait before the next round.
+    pub async fn refresh(&mut self) -> Duration {
+        match self.resolve().await {
+            Ok((endpoints, valid_until)) => {
+                self.reconciler.reconcile(endpoints).await;
+                valid_until
+                    .saturating_duration_since(Instant::now())
+                    .clamp(MIN_REFRESH, self.interval.max(MIN_REFRESH))
+            }
+            Err(e) => {
+                tracing::warn!(query = ?self.query, error = %e, "dns discovery failed to resolve");
+                self.interval.max(MIN_REFRESH)
+            }
+        }
+    }
+
+    async fn resolve(&self) -> Result<(Vec<DiscoveredEndpoint>, Instant), ResolveError> {
+        let result = match &self.query {
+            DnsQuery::Srv { name } => self.resolve_srv(name).await,
+            DnsQuery::Host { name, port } => self.resolve_host(name, *port).await,
+        };
+
+        match result {
+            Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                Ok((Vec::new(), Instant::now() + self.interval))
+            }
+            other => other,
+        }
+    }
+
+    /// Only the lowest-priority SRV targets are used; higher priorities are backups
+    /// per RFC 2782. SRV weights become endpoint weights (a weight of 0 still gets one share).
+    async fn resolve_srv(
+        &self,
+        name: &str,
+    ) -> Result<(Vec<DiscoveredEndpoint>, Instant), ResolveError> {
+        let lookup = self.resolver.srv_lookup(name).await?;
+
+        let best_priority = lookup.iter().map(|srv| srv.priority()).min();
+        let endpoints = lookup
+            .iter()
+            .filter(|srv| Some(srv.priority()) == best_priority)
+            .filter_map(|srv| {
+                let target = srv.target().to_utf8();
+                let host = target.trim_end_matches('.');
+                let uri = format!("{}://{}", self.scheme, format_host_port(host, srv.port()));
+                uri.parse()
+                    .ok()
+                    .map(|uri| DiscoveredEndpoint::new(uri).with_weight(srv.weight().into()))
+            })
+            .collect();
+
+        Ok((endpoints, lookup.as_lookup().valid_until()))
+    }
+
+    async fn resolve_host(
+        &self,
+        name: &str,
+        port: u16,
+    ) -> Result<(Vec<DiscoveredEndpoint>, Instant), ResolveError> {
+        let lookup = self.resolver.lookup_ip(name).await?;
+
+        let endpoints = lookup
+            .iter()
+            .filter_map(|ip| {
+                let uri = format!(
+                    "{}://{}",
+                    self.scheme,
+                    format_host_port(&ip.to_string(), port)
+                );
+                uri.parse().ok().map(DiscoveredEndpoint::new)
+            })
+            .collect();
+
+        Ok((endpoints, lookup.valid_until()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+        str::FromStr,
+        sync::Arc,
+    };
+
+    use axum::http::Uri;
+    use hickory_resolver::{
+        config::NameServerConfigGroup,
+        proto::{
+            op::{Message, MessageType, ResponseCode},
+            rr::{
+                rdata::{A, AAAA, SRV},
+                Name, RData, Record,
+            },
+        },
+    };
+    use tokio::{net::UdpSocket, sync::RwLock};
+
+    use super::*;
+    use crate::services::btreemap_endpoint_store::BTreeMapEndpointStore;
+
+    /// Minimal authoritative DNS server answering from a fixed record set over UDP.
+    async fn spawn_dns_stub(records: Vec<Record>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            loop {
+                let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+                let request = Message::from_vec(&buf[..len]).unwrap();
+                let query = request.queries()[0].clone();
+
+                let answers: Vec<Record> = records
+                    .iter()
+                    .filter(|r| r.name() == query.name() && r.record_type() == query.query_type())
+                    .cloned()
+                    .collect();
+
+                let mut response = Message::new();
+                response
+                    .set_id(request.id())
+                    .set_message_type(MessageType::Response)
+                    .set_op_code(request.op_code())
+                    .set_recursion_desired(request.recursion_desired())
+                    .set_recursion_available(true)
+                    .set_authoritative(true)
+                    .add_query(query);
+                if answers.is_empty() {
+                    response.set_response_code(ResponseCode::NXDomain);
+                }
+                response.add_answers(answers);
+
+                socket
+                    .send_to(&response.to_vec().unwrap(), peer)
+                    .await
+                    .unwrap();
+            }
+        });
+
+        address
+    }
+
+    fn stub_resolver(address: SocketAddr) -> TokioAsyncResolver {
+        let name_servers =
+            NameServerConfigGroup::from_ips_clear(&[address.ip()], address.port(), true);
+        let mut options = ResolverOpts::default();
+        options.cache_size = 0;
+        options.attempts = 1;
+        options.timeout = Duration::from_secs(1);
+        DnsDiscovery::resolver(
+            ResolverConfig::from_parts(None, vec![], name_servers),
+            options,
+        )
+    }
+
+    fn srv(priority: u16, weight: u16, port: u16, target: &str) -> Record {
+        Record::from_rdata(
+            Name::from_str("_http._tcp.backend.test.").unwrap(),
+            5,
+            RData::SRV(SRV::new(
+                priority,
+                weight,
+                port,
+                Name::from_str(target).unwrap(),
+            )),
+        )
+    }
+
+    async fn endpoints(store: &EndpointStoreType) -> Vec<(Uri, usize)> {
+        let mut endpoints: Vec<(Uri, usize)> = store
+            .read()
+            .await
+            .get_all_endpoints()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|ep| (ep.uri.clone(), ep.weight()))
+            .collect();
+        endpoints.sort_by_key(|(uri, _)| uri.to_string());
+        endpoints
+    }
+
+    #[tokio::test]
+    async fn test_srv_discovery_uses_best_priority_and_weights() {
+        let address = spawn_dns_stub(vec![
+            srv(10, 60, 8080, "a.backend.test."),
+            srv(10, 0, 8081, "b.backend.test."),
+            srv(20, 100, 8082, "backup.backend.test."),
+        ])
+        .await;
+
+        let store: EndpointStoreType = Arc::new(RwLock::new(BTreeMapEndpointStore::default()));
+        let mut discovery = DnsDiscovery::new(
+            stub_resolver(address),
+            DnsQuery::Srv {
+                name: "_http._tcp.backend.test.".to_string(),
+            },
+            "http",
+            Duration::from_secs(30),
+            store.clone(),
+        );
+
+        let delay = discovery.refresh().await;
+
+        // record TTL is 5s, well under the 30s interval
+        assert!(delay <= Duration::from_secs(5) && delay >= MIN_REFRESH);
+        assert_eq!(
+            endpoints(&store).await,
+            vec![
+                (Uri::from_static("http://a.backend.test:8080"), 60),
+                (Uri::from_static("http://b.backend.test:8081"), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_host_discovery_resolves_a_and_aaaa() {
+        let name = Name::from_str("web.backend.test.").unwrap();
+        let address = spawn_dns_stub(vec![
+            Record::from_rdata(name.clone(), 300, RData::A(A(Ipv4Addr::new(10, 0, 0, 1)))),
+            Record::from_rdata(name.clone(), 300, RData::A(A(Ipv4Addr::new(10, 0, 0, 2)))),
+            Record::from_rdata(name, 300, RData::AAAA(AAAA(Ipv6Addr::LOCALHOST))),
+        ])
+        .await;
+
+        let store: EndpointStoreType = Arc::new(RwLock::new(BTreeMapEndpointStore::default()));
+        let mut discovery = DnsDiscovery::new(
+            stub_resolver(address),
+            DnsQuery::Host {
+                name: "web.backend.test.".to_string(),
+                port: 9000,
+            },
+            "http",
+            Duration::from_secs(30),
+            store.clone(),
+        );
+
+        let delay = discovery.refresh().await;
+
+        // the interval caps the refresh when TTLs are longer
+        assert!(delay <= Duration::from_secs(30));
+        assert_eq!(
+            endpoints(&store).await,
+            vec![
+                (Uri::from_static("http://10.0.0.1:9000"), 1),
+                (Uri::from_static("http://10.0.0.2:9000"), 1),
+                (Uri::from_static("http://[::1]:9000"), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lookup_failure_keeps_existing_endpoints() {
+        let store: EndpointStoreType = Arc::new(RwLock::new(BTreeMapEndpointStore::default()));
+        let address = spawn_dns_stub(vec![srv(10, 1, 8080, "a.backend.test.")]).await;
+
+        let mut discovery = DnsDiscovery::new(
+            stub_resolver(address),
+            DnsQuery::Srv {
+                name: "_http._tcp.backend.test.".to_string(),
+            },
+            "http",
+            Duration::from_secs(30),
+            store.clone(),
+        );
+        discovery.refresh().await;
+        assert_eq!(endpoints(&store).await.len(), 1);
+
+        // nothing listens here, so the lookup times out rather than returning no records
+        let unreachable = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9);
+        discovery.resolver = stub_resolver(unreachable);
+        discovery.refresh().await;
+        assert_eq!(endpoints(&store).await.len(), 1);
+    }
+}
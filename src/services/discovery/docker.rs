@@ -0,0 +1,230 @@
+use std::{collections::HashMap, time::Duration};
+
+use axum::http::Uri;
+use bollard::{
+    container::ListContainersOptions, secret::ContainerSummary, system::EventsOptions, Docker,
+};
+use futures_util::StreamExt;
+
+use super::{format_host_port, DiscoveredEndpoint, Reconciler};
+use crate::app_state::EndpointStoreType;
+
+pub const POOL_LABEL: &str = "roundest-robin.pool";
+pub const PORT_LABEL: &str = "roundest-robin.port";
+pub const SCHEME_LABEL: &str = "roundest-robin.scheme";
+pub const WEIGHT_LABEL: &str = "roundest-robin.weight";
+
+/// Discovers backends from running containers labelled `roundest-robin.pool=<pool>`.
+///
+/// Container start/stop events trigger an immediate reconcile; a periodic reconcile
+/// catches anything missed while the event stream was down.
+pub struct DockerDiscovery {
+    docker: Docker,
+    pool: String,
+    reconcile_interval: Duration,
+    reconciler: Reconciler,
+}
+
+impl DockerDiscovery {
+    pub fn new(
+        docker: Docker,
+        pool: &str,
+        reconcile_interval: Duration,
+        store: EndpointStoreType,
+    ) -> Self {
+        Self {
+            docker,
+            pool: pool.to_string(),
+            reconcile_interval,
+            reconciler: Reconciler::new("docker", store),
+        }
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.reconcile_interval);
+
+        loop {
+            let mut events = self.docker.events(Some(EventsOptions::<String> {
+                filters: HashMap::from([
+                    ("type".to_string(), vec!["container".to_string()]),
+                    (
+                        "event".to_string(),
+                        vec![
+                            "start".to_string(),
+                            "die".to_string(),
+                            "pause".to_string(),
+                            "unpause".to_string(),
+                        ],
+                    ),
+                    ("label".to_string(), vec![self.pool_filter()]),
+                ]),
+                ..Default::default()
+            }));
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => self.reconcile().await,
+                    event = events.next() => match event {
+                        Some(Ok(_)) => self.reconcile().await,
+                        Some(Err(e)) => {
+                            tracing::warn!(error = %e, "docker event stream failed");
+                            break;
+                        }
+                        None => break,
+                    },
+                }
+            }
+
+            // the periodic reconcile keeps running until the event stream is back
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn reconcile(&mut self) {
+        match self.discover().await {
+            Ok(uris) => self.reconciler.reconcile(uris).await,
+            Err(e) => tracing::warn!(error = %e, "docker discovery failed to list containers"),
+        }
+    }
+
+    async fn discover(&self) -> Result<Vec<DiscoveredEndpoint>, bollard::errors::Error> {
+        let containers = self
+            .docker
+            .list_containers(Some(ListContainersOptions::<String> {
+                all: false, // running only
+                filters: HashMap::from([
+                    ("label".to_string(), vec![self.pool_filter()]),
+                    ("status".to_string(), vec!["running".to_string()]),
+                ]),
+                ..Default::default()
+            }))
+            .await?;
+
+        Ok(containers
+            .iter()
+            .filter_map(|container| {
+                let uri = container_endpoint(container)?;
+                let weight = container
+                    .labels
+                    .as_ref()
+                    .and_then(|labels| labels.get(WEIGHT_LABEL))
+                    .and_then(|weight| weight.parse().ok())
+                    .unwrap_or(1);
+                Some(DiscoveredEndpoint::new(uri).with_weight(weight))
+            })
+            .collect())
+    }
+
+    fn pool_filter(&self) -> String {
+        format!("{}={}", POOL_LABEL, self.pool)
+    }
+}
+
+/// Work out where a labelled container can be reached.
+///
+/// A published host port for `roundest-robin.port` is preferred, so a router running
+/// on the host works out of the box; otherwise the container's network IP is used,
+/// which suits a router attached to the same Docker network.
+pub fn container_endpoint(container: &ContainerSummary) -> Option<Uri> {
+    let labels = container.labels.as_ref()?;
+    let port: u16 = labels.get(PORT_LABEL)?.parse().ok()?;
+    let scheme = labels
+        .get(SCHEME_LABEL)
+        .map(String::as_str)
+        .unwrap_or("http");
+
+    let published = container.ports.iter().flatten().find_map(|p| {
+        if p.private_port != port {
+            return None;
+        }
+        let host = match p.ip.as_deref() {
+            None | Some("") | Some("0.0.0.0") | Some("::") => "localhost",
+            Some(ip) => ip,
+        };
+        p.public_port.map(|public| format_host_port(host, public))
+    });
+
+    let authority = published.or_else(|| {
+        container
+            .network_settings
+            .as_ref()?
+            .networks
+            .as_ref()?
+            .values()
+            .filter_map(|network| network.ip_address.as_deref())
+            .find(|ip| !ip.is_empty())
+            .map(|ip| format_host_port(ip, port))
+    })?;
+
+    format!("{}://{}", scheme, authority).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use bollard::secret::{ContainerSummaryNetworkSettings, EndpointSettings, Port, PortTypeEnum};
+
+    use super::*;
+
+    fn labelled(port: &str) -> ContainerSummary {
+        ContainerSummary {
+            labels: Some(HashMap::from([
+                (POOL_LABEL.to_string(), "web".to_string()),
+                (PORT_LABEL.to_string(), port.to_string()),
+            ])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_container_endpoint_prefers_published_port() {
+        let mut container = labelled("8000");
+        container.ports = Some(vec![
+            Port {
+                ip: Some("0.0.0.0".to_string()),
+                private_port: 9000,
+                public_port: Some(19000),
+                typ: Some(PortTypeEnum::TCP),
+            },
+            Port {
+                ip: Some("0.0.0.0".to_string()),
+                private_port: 8000,
+                public_port: Some(18000),
+                typ: Some(PortTypeEnum::TCP),
+            },
+        ]);
+
+        assert_eq!(
+            container_endpoint(&container).unwrap(),
+            Uri::from_static("http://localhost:18000")
+        );
+    }
+
+    #[test]
+    fn test_container_endpoint_falls_back_to_network_ip() {
+        let mut container = labelled("8000");
+        container.network_settings = Some(ContainerSummaryNetworkSettings {
+            networks: Some(HashMap::from([(
+                "bridge".to_string(),
+                EndpointSettings {
+                    ip_address: Some("172.17.0.5".to_string()),
+                    ..Default::default()
+                },
+            )])),
+        });
+
+        assert_eq!(
+            container_endpoint(&container).unwrap(),
+            Uri::from_static("http://172.17.0.5:8000")
+        );
+    }
+
+    #[test]
+    fn test_container_endpoint_requires_port_label() {
+        assert!(container_endpoint(&labelled("not-a-port")).is_none());
+        assert!(container_endpoint(&ContainerSummary::default()).is_none());
+    }
+}
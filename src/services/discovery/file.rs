@@ -0,0 +1,232 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::Deserialize;
+
+use super::{DiscoveredEndpoint, Reconciler};
+use crate::{app_state::EndpointStoreType, domain::Endpoint};
+
+/// Follows a file of backend URIs that other tooling rewrites.
+///
+/// Two formats are accepted:
+///
+/// * plain text, one `uri [weight]` per line, with `#` comments and blank lines ignored
+/// * JSON (a `.json` file, or content starting with `[`), an array of URI strings or
+///   `{"uri": "...", "weight": 2}` objects
+///
+/// Malformed entries are logged and skipped. A file that cannot be read, or JSON that
+/// does not parse at all, leaves the current endpoints untouched.
+pub struct FileDiscovery {
+    path: PathBuf,
+    poll_interval: Duration,
+    reconciler: Reconciler,
+    last_content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonEntry {
+    Uri(String),
+    Weighted { uri: String, weight: Option<usize> },
+    Other(serde_json::Value),
+}
+
+impl FileDiscovery {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        poll_interval: Duration,
+        store: EndpointStoreType,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            poll_interval,
+            reconciler: Reconciler::new("file", store),
+            last_content: None,
+        }
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.poll_interval);
+        loop {
+            ticker.tick().await;
+            self.poll().await;
+        }
+    }
+
+    /// Reload the file if its content changed; otherwise just finish off drained endpoints.
+    pub async fn poll(&mut self) {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) => {
+                tracing::warn!(path = %self.path.display(), error = %e, "file discovery failed to read");
+                return;
+            }
+        };
+
+        if self.last_content.as_deref() == Some(content.as_str()) {
+            self.reconciler.sweep().await;
+            return;
+        }
+
+        match self.parse(&content) {
+            Ok(endpoints) => {
+                self.reconciler.reconcile(endpoints).await;
+                self.last_content = Some(content);
+            }
+            Err(e) => {
+                tracing::warn!(path = %self.path.display(), error = %e, "file discovery ignoring unparseable file")
+            }
+        }
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<DiscoveredEndpoint>, serde_json::Error> {
+        let is_json = self
+            .path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+            || content.trim_start().starts_with('[');
+
+        if is_json {
+            parse_json(content)
+        } else {
+            Ok(parse_text(content))
+        }
+    }
+}
+
+fn parse_text(content: &str) -> Vec<DiscoveredEndpoint> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                return None;
+            }
+
+            let mut fields = line.split_whitespace();
+            let uri = fields.next().and_then(Endpoint::parse_uri);
+            let weight = match fields.next() {
+                Some(weight) => weight.parse().ok(),
+                None => Some(1),
+            };
+
+            match (uri, weight, fields.next()) {
+                (Some(uri), Some(weight), None) => {
+                    Some(DiscoveredEndpoint::new(uri).with_weight(weight))
+                }
+                _ => {
+                    tracing::warn!(line = index + 1, content = line, "skipping malformed line");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn parse_json(content: &str) -> Result<Vec<DiscoveredEndpoint>, serde_json::Error> {
+    let entries: Vec<JsonEntry> = serde_json::from_str(content)?;
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| {
+            let (uri, weight) = match &entry {
+                JsonEntry::Uri(uri) => (uri.as_str(), 1),
+                JsonEntry::Weighted { uri, weight } => (uri.as_str(), weight.unwrap_or(1)),
+                JsonEntry::Other(value) => {
+                    tracing::warn!(entry = %value, "skipping malformed entry");
+                    return None;
+                }
+            };
+            match Endpoint::parse_uri(uri) {
+                Some(uri) => Some(DiscoveredEndpoint::new(uri).with_weight(weight)),
+                None => {
+                    tracing::warn!(entry = ?entry, "skipping malformed entry");
+                    None
+                }
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::Uri;
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::services::btreemap_endpoint_store::BTreeMapEndpointStore;
+
+    #[test]
+    fn test_parse_text_skips_malformed_lines() {
+        let endpoints = parse_text(
+            "# backends\nhttp://a:80\n\nhttp://b:80 3 # heavier\nnot a uri\nhttp://c:80 heavy\n",
+        );
+
+        assert_eq!(
+            endpoints,
+            vec![
+                DiscoveredEndpoint::new(Uri::from_static("http://a:80")),
+                DiscoveredEndpoint::new(Uri::from_static("http://b:80")).with_weight(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_json_skips_malformed_entries() {
+        let endpoints = parse_json(
+            r#"["http://a:80", {"uri": "http://b:80", "weight": 2}, {"uri": "/relative"}, 42]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            endpoints,
+            vec![
+                DiscoveredEndpoint::new(Uri::from_static("http://a:80")),
+                DiscoveredEndpoint::new(Uri::from_static("http://b:80")).with_weight(2),
+            ]
+        );
+        assert!(parse_json("[\"http://a:80\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_poll_follows_file_and_ignores_broken_rewrites() {
+        let path = std::env::temp_dir().join(format!("endpoints-{}.json", uuid::Uuid::new_v4()));
+        let store: EndpointStoreType = Arc::new(RwLock::new(BTreeMapEndpointStore::default()));
+        let mut discovery = FileDiscovery::new(&path, Duration::from_secs(1), store.clone());
+
+        std::fs::write(&path, r#"["http://a:80", "http://b:80"]"#).unwrap();
+        discovery.poll().await;
+        assert_eq!(uris(&store).await, vec!["http://a:80/", "http://b:80/"]);
+
+        // a half-written file must not disturb the current set
+        std::fs::write(&path, r#"["http://a:80""#).unwrap();
+        discovery.poll().await;
+        assert_eq!(uris(&store).await, vec!["http://a:80/", "http://b:80/"]);
+
+        std::fs::write(&path, r#"["http://b:80", "http://c:80"]"#).unwrap();
+        discovery.poll().await;
+        assert_eq!(uris(&store).await, vec!["http://b:80/", "http://c:80/"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    async fn uris(store: &EndpointStoreType) -> Vec<String> {
+        let mut uris: Vec<String> = store
+            .read()
+            .await
+            .get_all_endpoints()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|ep| ep.uri.to_string())
+            .collect();
+        uris.sort();
+        uris
+    }
+}
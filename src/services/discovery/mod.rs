@@ -0,0 +1,257 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::http::Uri;
+
+use crate::{
+    app_state::EndpointStoreType,
+    domain::{Endpoint, EndpointStoreError},
+};
+
+pub mod dns;
+pub mod docker;
+pub mod file;
+
+/// An endpoint reported by a discovery source, with its relative weight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredEndpoint {
+    pub uri: Uri,
+    pub weight: usize,
+}
+
+impl DiscoveredEndpoint {
+    pub fn new(uri: Uri) -> Self {
+        Self { uri, weight: 1 }
+    }
+
+    pub fn with_weight(self, weight: usize) -> Self {
+        Self { weight, ..self }
+    }
+}
+
+/// `host:port`, bracketing IPv6 literals.
+pub(crate) fn format_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Applies the endpoint set reported by a discovery source to an endpoint store.
+///
+/// Only endpoints this source added are ever touched, so statically configured
+/// endpoints and those owned by other sources are left alone. Endpoints that drop out
+/// of the source are drained first and removed once their in-flight requests finish.
+/// Drains started elsewhere, such as over the admin API, are never cancelled here.
+pub struct Reconciler {
+    source: &'static str,
+    store: EndpointStoreType,
+    owned: HashSet<Uri>,
+    /// Owned endpoints that dropped out of the source, and whether this drained them.
+    vanished: HashMap<Uri, bool>,
+}
+
+impl Reconciler {
+    pub fn new(source: &'static str, store: EndpointStoreType) -> Self {
+        Self {
+            source,
+            store,
+            owned: HashSet::new(),
+            vanished: HashMap::new(),
+        }
+    }
+
+    pub async fn reconcile(&mut self, discovered: Vec<DiscoveredEndpoint>) {
+        let weights: HashMap<Uri, usize> = discovered
+            .into_iter()
+            .map(|ep| (ep.uri, ep.weight))
+            .collect();
+        let mut store = self.store.write().await;
+        let existing = store.get_all_endpoints().await.unwrap_or_default();
+
+        self.owned
+            .retain(|uri| existing.iter().any(|ep| &ep.uri == uri));
+        let owned = &self.owned;
+        self.vanished.retain(|uri, _| owned.contains(uri));
+
+        for endpoint in existing.iter().filter(|ep| self.owned.contains(&ep.uri)) {
+            match weights.get(&endpoint.uri) {
+                // weights may change between rounds, e.g. when SRV records are re-weighted
+                Some(weight) => {
+                    endpoint.set_weight(*weight);
+                    if self.vanished.remove(&endpoint.uri) == Some(true) {
+                        endpoint.cancel_drain();
+                        tracing::info!(source = self.source, endpoint = %endpoint.uri, "restored endpoint");
+                    }
+                }
+                None if !self.vanished.contains_key(&endpoint.uri) => {
+                    let drained_here = !endpoint.is_draining();
+                    if drained_here {
+                        endpoint.drain();
+                        tracing::info!(source = self.source, endpoint = %endpoint.uri, "draining endpoint");
+                    }
+                    self.vanished.insert(endpoint.uri.clone(), drained_here);
+                }
+                None => {}
+            }
+        }
+
+        for (uri, weight) in weights.iter() {
+            if self.owned.contains(uri) {
+                continue;
+            }
+            let endpoint = Endpoint::new(uri.clone()).with_weight(*weight);
+            match store.add_endpoint(endpoint).await {
+                Ok(()) => tracing::info!(source = self.source, endpoint = %uri, "added endpoint"),
+                // already configured elsewhere; not ours to manage
+                Err(EndpointStoreError::EndpointAlreadyExists) => continue,
+                Err(e) => {
+                    tracing::warn!(source = self.source, endpoint = %uri, error = ?e, "failed to add endpoint");
+                    continue;
+                }
+            }
+            self.owned.insert(uri.clone());
+        }
+
+        drop(store);
+        self.sweep().await;
+    }
+
+    /// Remove vanished endpoints that no longer have requests in flight.
+    pub async fn sweep(&mut self) {
+        let mut store = self.store.write().await;
+        let drained: Vec<Uri> = store
+            .get_all_endpoints()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|ep| {
+                self.vanished.contains_key(&ep.uri)
+                    && ep.is_draining()
+                    && ep.concurrent_connection_count() == 0
+            })
+            .map(|ep| ep.uri)
+            .collect();
+
+        for uri in drained {
+            if store.remove_endpoint(&uri).await.is_ok() {
+                tracing::info!(source = self.source, endpoint = %uri, "removed endpoint");
+            }
+            self.owned.remove(&uri);
+            self.vanished.remove(&uri);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::services::btreemap_endpoint_store::BTreeMapEndpointStore;
+
+    #[tokio::test]
+    async fn test_reconcile_adds_and_removes_owned_endpoints() {
+        let store: EndpointStoreType = Arc::new(RwLock::new(BTreeMapEndpointStore::default()));
+        let static_uri = Uri::from_static("http://static:80");
+        store
+            .write()
+            .await
+            .add_endpoint(Endpoint::new(static_uri.clone()))
+            .await
+            .unwrap();
+
+        let mut reconciler = Reconciler::new("test", store.clone());
+        let a = Uri::from_static("http://a:80");
+        let b = Uri::from_static("http://b:80");
+
+        reconciler
+            .reconcile(vec![
+                DiscoveredEndpoint::new(a.clone()),
+                DiscoveredEndpoint::new(b.clone()),
+                DiscoveredEndpoint::new(static_uri.clone()),
+            ])
+            .await;
+        let uris = endpoint_uris(&store).await;
+        assert_eq!(uris.len(), 3);
+
+        // static endpoint was never ours, so dropping it from the source must not remove it
+        reconciler
+            .reconcile(vec![DiscoveredEndpoint::new(b.clone()).with_weight(5)])
+            .await;
+        let uris = endpoint_uris(&store).await;
+        assert_eq!(uris, vec![b, static_uri]);
+
+        let endpoints = store.read().await.get_all_endpoints().await.unwrap();
+        assert_eq!(endpoints[0].weight(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_keeps_drains_it_did_not_start() {
+        let store: EndpointStoreType = Arc::new(RwLock::new(BTreeMapEndpointStore::default()));
+        let mut reconciler = Reconciler::new("test", store.clone());
+        let a = Uri::from_static("http://a:80");
+        let b = Uri::from_static("http://b:80");
+        let discovered = || {
+            vec![
+                DiscoveredEndpoint::new(a.clone()),
+                DiscoveredEndpoint::new(b.clone()),
+            ]
+        };
+
+        reconciler.reconcile(discovered()).await;
+        let endpoints = store.read().await.get_all_endpoints().await.unwrap();
+        // drained over the admin API, e.g. ahead of stopping the replica
+        endpoints[0].drain();
+
+        reconciler.reconcile(discovered()).await;
+        assert_eq!(endpoint_uris(&store).await, vec![a.clone(), b.clone()]);
+        assert!(endpoints[0].is_draining());
+
+        // one the reconciler drained itself is restored when it comes back
+        endpoints[1].increase_concurrent_connection_count();
+        reconciler
+            .reconcile(vec![DiscoveredEndpoint::new(a.clone())])
+            .await;
+        assert!(endpoints[1].is_draining());
+        reconciler.reconcile(discovered()).await;
+        assert!(!endpoints[1].is_draining());
+        assert!(endpoints[0].is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_drains_before_removing() {
+        let store: EndpointStoreType = Arc::new(RwLock::new(BTreeMapEndpointStore::default()));
+        let mut reconciler = Reconciler::new("test", store.clone());
+        let a = Uri::from_static("http://a:80");
+
+        reconciler
+            .reconcile(vec![DiscoveredEndpoint::new(a.clone())])
+            .await;
+        let endpoint = store.read().await.get_all_endpoints().await.unwrap()[0].clone();
+        endpoint.increase_concurrent_connection_count();
+
+        // still serving a request, so it stays in the store but gets no new traffic
+        reconciler.reconcile(vec![]).await;
+        assert_eq!(endpoint_uris(&store).await, vec![a.clone()]);
+        assert!(endpoint.is_draining());
+        assert!(store.read().await.get_next_endpoint().await.is_err());
+
+        endpoint.decrease_concurrent_connection_count();
+        reconciler.sweep().await;
+        assert!(endpoint_uris(&store).await.is_empty());
+    }
+
+    async fn endpoint_uris(store: &EndpointStoreType) -> Vec<Uri> {
+        store
+            .read()
+            .await
+            .get_all_endpoints()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|ep| ep.uri)
+            .collect()
+    }
+}
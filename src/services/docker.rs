@@ -0,0 +1,142 @@
+use std::{path::PathBuf, time::Duration};
+
+use bollard::{errors::Error, Docker, API_DEFAULT_VERSION};
+
+use crate::utils::constants::{
+    DOCKER_CERT_PATH, DOCKER_ENABLED, DOCKER_HOST, DOCKER_TIMEOUT_SECS, DOCKER_TLS_VERIFY,
+};
+
+const DEFAULT_SOCKET: &str = "unix:///var/run/docker.sock";
+
+/// How to reach the Docker daemon used for stats and discovery.
+#[derive(Debug, Clone)]
+pub struct DockerSettings {
+    /// `unix://`, `tcp://`, `http://` or `https://` address; the local socket when unset.
+    pub host: Option<String>,
+    pub timeout: Duration,
+    /// Directory holding `key.pem`, `cert.pem` and `ca.pem` for TLS daemons; `~/.docker` when unset.
+    pub cert_path: Option<PathBuf>,
+    pub tls_verify: bool,
+}
+
+impl DockerSettings {
+    /// Settings from the environment, or `None` when Docker has been switched off.
+    pub fn from_env() -> Option<Self> {
+        if !*DOCKER_ENABLED {
+            return None;
+        }
+
+        Some(Self {
+            host: DOCKER_HOST.clone(),
+            timeout: Duration::from_secs(*DOCKER_TIMEOUT_SECS),
+            cert_path: DOCKER_CERT_PATH.as_ref().map(PathBuf::from),
+            tls_verify: *DOCKER_TLS_VERIFY,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum DockerAddress<'a> {
+    Unix(&'a str),
+    Http(&'a str),
+    Tls(&'a str),
+}
+
+impl<'a> DockerAddress<'a> {
+    fn parse(host: Option<&'a str>, tls: bool) -> Result<Self, Error> {
+        let host = host.unwrap_or(DEFAULT_SOCKET);
+
+        if let Some(path) = host.strip_prefix("unix://") {
+            return Ok(DockerAddress::Unix(path));
+        }
+        if host.starts_with('/') {
+            return Ok(DockerAddress::Unix(host));
+        }
+
+        let address = ["tcp://", "http://", "https://"]
+            .iter()
+            .find_map(|scheme| host.strip_prefix(scheme))
+            .ok_or_else(|| Error::UnsupportedURISchemeError {
+                uri: host.to_string(),
+            })?;
+
+        if tls || host.starts_with("https://") {
+            Ok(DockerAddress::Tls(address))
+        } else {
+            Ok(DockerAddress::Http(address))
+        }
+    }
+}
+
+/// Build a Docker client. No request is made, so this succeeds even when the daemon is down.
+pub fn connect_docker(settings: &DockerSettings) -> Result<Docker, Error> {
+    let timeout = settings.timeout.as_secs().max(1);
+
+    match DockerAddress::parse(settings.host.as_deref(), settings.tls_verify)? {
+        DockerAddress::Unix(path) => Docker::connect_with_unix(path, timeout, API_DEFAULT_VERSION),
+        DockerAddress::Http(address) => {
+            Docker::connect_with_http(address, timeout, API_DEFAULT_VERSION)
+        }
+        DockerAddress::Tls(address) => {
+            let cert_path = settings
+                .cert_path
+                .clone()
+                .or_else(|| {
+                    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".docker"))
+                })
+                .ok_or(Error::NoHomePathError)?;
+            Docker::connect_with_ssl(
+                address,
+                &cert_path.join("key.pem"),
+                &cert_path.join("cert.pem"),
+                &cert_path.join("ca.pem"),
+                timeout,
+                API_DEFAULT_VERSION,
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker_address() {
+        assert_eq!(
+            DockerAddress::parse(None, false).unwrap(),
+            DockerAddress::Unix("/var/run/docker.sock")
+        );
+        assert_eq!(
+            DockerAddress::parse(Some("unix:///run/user/1000/docker.sock"), false).unwrap(),
+            DockerAddress::Unix("/run/user/1000/docker.sock")
+        );
+        assert_eq!(
+            DockerAddress::parse(Some("tcp://10.0.0.2:2375"), false).unwrap(),
+            DockerAddress::Http("10.0.0.2:2375")
+        );
+        assert_eq!(
+            DockerAddress::parse(Some("tcp://10.0.0.2:2376"), true).unwrap(),
+            DockerAddress::Tls("10.0.0.2:2376")
+        );
+        assert_eq!(
+            DockerAddress::parse(Some("https://docker.internal:2376"), false).unwrap(),
+            DockerAddress::Tls("docker.internal:2376")
+        );
+        assert!(DockerAddress::parse(Some("npipe:////./pipe/docker_engine"), false).is_err());
+    }
+
+    #[test]
+    fn test_tls_requires_readable_certificates() {
+        let settings = DockerSettings {
+            host: Some("tcp://10.0.0.2:2376".to_string()),
+            timeout: Duration::from_secs(5),
+            cert_path: Some(std::env::temp_dir().join("no-such-docker-certs")),
+            tls_verify: true,
+        };
+        assert!(matches!(
+            connect_docker(&settings),
+            Err(Error::CertPathError { .. })
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashMap, time::Duration};
+
+use bollard::{
+    container::{ListContainersOptions, StatsOptions},
+    Docker,
+};
+use futures_util::StreamExt;
+use tokio::task::JoinHandle;
+
+use crate::{
+    app_state::{DockerStatsCache, EndpointStoreType},
+    domain::{ContainerIndex, ContainerRef, DockerStats, ResourceSample},
+};
+
+/// Keeps a live stats stream open for every running container and publishes the
+/// latest sample of each into a shared [`DockerStatsCache`].
+///
+/// The container list is refreshed every `refresh_interval`: streams are started for
+/// new containers, restarted if they ended, and stopped for containers that are gone.
+/// Each sample is also recorded on the endpoints the container serves in any attached
+/// endpoint store, for resource-aware balancing.
+pub struct DockerStatsCollector {
+    docker: Docker,
+    refresh_interval: Duration,
+    cache: DockerStatsCache,
+    stores: Vec<EndpointStoreType>,
+    streams: HashMap<String, JoinHandle<()>>,
+}
+
+impl DockerStatsCollector {
+    pub fn new(docker: Docker, refresh_interval: Duration, cache: DockerStatsCache) -> Self {
+        Self {
+            docker,
+            refresh_interval,
+            cache,
+            stores: Vec::new(),
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Can be called once per pool.
+    pub fn with_endpoint_store(mut self, store: EndpointStoreType) -> Self {
+        self.stores.push(store);
+        self
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.refresh_interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.refresh().await {
+                tracing::warn!(error = %e, "stats collector failed to list containers");
+            }
+        }
+    }
+
+    async fn refresh(&mut self) -> Result<(), bollard::errors::Error> {
+        let containers = self
+            .docker
+            .list_containers(Some(ListContainersOptions::<String> {
+                all: false, // running only
+                ..Default::default()
+            }))
+            .await?;
+
+        let running: Vec<ContainerRef> = containers
+            .iter()
+            .filter_map(ContainerRef::from_summary)
+            .collect();
+
+        self.streams.retain(|id, stream| {
+            let keep = running.iter().any(|container| &container.id == id);
+            if !keep {
+                stream.abort();
+            }
+            keep && !stream.is_finished()
+        });
+
+        {
+            let mut snapshot = self.cache.write().await;
+            snapshot.index = ContainerIndex::new(&containers);
+            snapshot
+                .stats
+                .retain(|id, _| running.iter().any(|container| &container.id == id));
+        }
+
+        for container in running {
+            if !self.streams.contains_key(&container.id) {
+                let stream = tokio::spawn(stream_stats(
+                    self.docker.clone(),
+                    container.clone(),
+                    self.cache.clone(),
+                    self.stores.clone(),
+                ));
+                self.streams.insert(container.id, stream);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Follow one container's stats stream until it ends, e.g. when the container stops.
+async fn stream_stats(
+    docker: Docker,
+    container: ContainerRef,
+    cache: DockerStatsCache,
+    stores: Vec<EndpointStoreType>,
+) {
+    let mut frames = docker.stats(
+        &container.id,
+        Some(StatsOptions {
+            stream: true,
+            one_shot: false,
+        }),
+    );
+
+    while let Some(frame) = frames.next().await {
+        match frame {
+            Ok(frame) => {
+                let stats = DockerStats::from_frame(&container, &frame);
+                for store in &stores {
+                    record_on_endpoints(store, &cache, &stats).await;
+                }
+                cache
+                    .write()
+                    .await
+                    .stats
+                    .insert(container.id.clone(), stats);
+            }
+            Err(e) => {
+                tracing::warn!(container = %container.name, error = %e, "stats stream failed");
+                break;
+            }
+        }
+    }
+
+    // a sample that is no longer updated would be misleading
+    cache.write().await.stats.remove(&container.id);
+}
+
+async fn record_on_endpoints(
+    store: &EndpointStoreType,
+    cache: &DockerStatsCache,
+    stats: &DockerStats,
+) {
+    let Ok(endpoints) = store.read().await.get_all_endpoints().await else {
+        return;
+    };
+    let snapshot = cache.read().await;
+
+    for endpoint in endpoints {
+        let served_here = snapshot
+            .index
+            .find(&endpoint.uri)
+            .is_some_and(|container| container.id == stats.container_id);
+        if served_here {
+            endpoint.record_resources(ResourceSample {
+                cpu_percentage: stats.cpu_percentage,
+                memory_percentage: stats.memory_percentage,
+                sampled_at: stats.sampled_at,
+            });
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::{collections::HashMap, io, path::Path, sync::RwLock};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+
+use crate::{
+    domain::RouterError,
+    utils::constants::{ERROR_PAGES_DIR, MAINTENANCE_MODE},
+};
+
+/// The responses the router gives itself when no backend answers the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorPage {
+    BadGateway,
+    ServiceUnavailable,
+    GatewayTimeout,
+    Maintenance,
+}
+
+impl ErrorPage {
+    const ALL: [ErrorPage; 4] = [
+        ErrorPage::BadGateway,
+        ErrorPage::ServiceUnavailable,
+        ErrorPage::GatewayTimeout,
+        ErrorPage::Maintenance,
+    ];
+
+    /// The page for a proxy error, `None` for errors that keep their usual response.
+    pub fn for_error(error: &RouterError) -> Option<Self> {
+        match error {
+            RouterError::BadGateway => Some(ErrorPage::BadGateway),
+            RouterError::NoEndpointAvailable => Some(ErrorPage::ServiceUnavailable),
+            RouterError::GatewayTimeout => Some(ErrorPage::GatewayTimeout),
+            RouterError::Maintenance => Some(ErrorPage::Maintenance),
+            _ => None,
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorPage::BadGateway => StatusCode::BAD_GATEWAY,
+            ErrorPage::ServiceUnavailable | ErrorPage::Maintenance => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            ErrorPage::GatewayTimeout => StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+
+    /// File name in `ERROR_PAGES_DIR`, without the extension.
+    fn file_stem(self) -> &'static str {
+        match self {
+            ErrorPage::BadGateway => "502",
+            ErrorPage::ServiceUnavailable => "503",
+            ErrorPage::GatewayTimeout => "504",
+            ErrorPage::Maintenance => "maintenance",
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            ErrorPage::BadGateway => "Bad gateway",
+            ErrorPage::ServiceUnavailable => "Service unavailable",
+            ErrorPage::GatewayTimeout => "Gateway timeout",
+            ErrorPage::Maintenance => "Down for maintenance",
+        }
+    }
+
+    fn default_body(self, format: Format) -> Bytes {
+        match format {
+            Format::Json => serde_json::json!({ "error": self.message() })
+                .to_string()
+                .into(),
+            Format::Html => format!(
+                "<!DOCTYPE html>\n<html>\n<head><title>{status}</title></head>\n\
+                 <body><h1>{status}</h1><p>{message}.</p></body>\n</html>\n",
+                status = self.status(),
+                message = self.message(),
+            )
+            .into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Html,
+    Json,
+}
+
+impl Format {
+    const ALL: [Format; 2] = [Format::Html, Format::Json];
+
+    /// HTML when the client's `Accept` ranks it above JSON, otherwise JSON.
+    pub fn negotiate(accept: Option<&HeaderValue>) -> Self {
+        let Some(accept) = accept.and_then(|accept| accept.to_str().ok()) else {
+            return Format::Json;
+        };
+        if quality(accept, "text", "html") > quality(accept, "application", "json") {
+            Format::Html
+        } else {
+            Format::Json
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Json => "json",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Html => "text/html; charset=utf-8",
+            Format::Json => "application/json",
+        }
+    }
+}
+
+/// The `q` of the most specific range in `accept` that covers `kind/subtype`, 0 if none does.
+fn quality(accept: &str, kind: &str, subtype: &str) -> f32 {
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';');
+            let (range_kind, range_subtype) = params.next()?.trim().split_once('/')?;
+            let specificity = match (range_kind, range_subtype) {
+                (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => 2,
+                (k, "*") if k.eq_ignore_ascii_case(kind) => 1,
+                ("*", "*") => 0,
+                _ => return None,
+            };
+            let q = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((specificity, q))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, q)| q)
+}
+
+/// Error page bodies, and whether the router is in maintenance mode.
+///
+/// Pages not given fall back to a short built-in body.
+#[derive(Debug, Default)]
+pub struct ErrorPages {
+    pages: HashMap<(ErrorPage, Format), Bytes>,
+    /// When maintenance mode was switched on; `None` while it is off.
+    maintenance: RwLock<Option<DateTime<Utc>>>,
+}
+
+impl ErrorPages {
+    /// Read `502`, `503`, `504` and `maintenance` pages, each as `.html` and `.json`, from
+    /// `dir`. Missing files are left to the built-in pages.
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut pages = HashMap::new();
+        for page in ErrorPage::ALL {
+            for format in Format::ALL {
+                let path =
+                    dir.as_ref()
+                        .join(format!("{}.{}", page.file_stem(), format.extension()));
+                match std::fs::read(&path) {
+                    Ok(body) => {
+                        pages.insert((page, format), Bytes::from(body));
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        return Err(io::Error::new(
+                            e.kind(),
+                            format!("{}: {}", path.display(), e),
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            pages,
+            ..Default::default()
+        })
+    }
+
+    pub fn from_env() -> io::Result<Self> {
+        let pages = match ERROR_PAGES_DIR.as_deref() {
+            Some(dir) => Self::load(dir)?,
+            None => Self::default(),
+        };
+        pages.set_maintenance(*MAINTENANCE_MODE);
+        Ok(pages)
+    }
+
+    pub fn maintenance_since(&self) -> Option<DateTime<Utc>> {
+        *self.maintenance.read().expect("maintenance lock poisoned")
+    }
+
+    /// Switch maintenance mode on or off; switching it on again keeps the original time.
+    pub fn set_maintenance(&self, enabled: bool) {
+        let mut maintenance = self.maintenance.write().expect("maintenance lock poisoned");
+        match (enabled, *maintenance) {
+            (true, None) => *maintenance = Some(Utc::now()),
+            (false, _) => *maintenance = None,
+            (true, Some(_)) => {}
+        }
+    }
+
+    /// `page` in the format `accept` prefers.
+    pub fn response(&self, page: ErrorPage, accept: Option<&HeaderValue>) -> Response {
+        let format = Format::negotiate(accept);
+        let body = self
+            .pages
+            .get(&(page, format))
+            .cloned()
+            .unwrap_or_else(|| page.default_body(format));
+
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = page.status();
+        let headers = response.headers_mut();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(format.content_type()),
+        );
+        // a cached error page would outlive the outage
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        headers.insert(header::VARY, HeaderValue::from_static("accept"));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_error_pages() {
+        let browser = HeaderValue::from_static(
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        );
+        assert_eq!(Format::negotiate(Some(&browser)), Format::Html);
+        assert_eq!(Format::negotiate(None), Format::Json);
+        for (accept, expected) in [
+            ("*/*", Format::Json),
+            ("application/json", Format::Json),
+            ("text/*", Format::Html),
+            ("text/html;q=0.5, application/json", Format::Json),
+            ("text/html, */*;q=0.1", Format::Html),
+        ] {
+            let accept = HeaderValue::from_static(accept);
+            assert_eq!(Format::negotiate(Some(&accept)), expected, "{:?}", accept);
+        }
+
+        let dir = std::env::temp_dir().join(format!("error-pages-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("maintenance.html"), "<h1>Back at 6</h1>").unwrap();
+        let pages = ErrorPages::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let response = pages.response(ErrorPage::Maintenance, Some(&browser));
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "<h1>Back at 6</h1>");
+
+        let response = pages.response(ErrorPage::GatewayTimeout, None);
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, r#"{"error":"Gateway timeout"}"#);
+
+        assert!(pages.maintenance_since().is_none());
+        pages.set_maintenance(true);
+        let since = pages.maintenance_since().unwrap();
+        pages.set_maintenance(true);
+        assert_eq!(pages.maintenance_since(), Some(since));
+        pages.set_maintenance(false);
+        assert!(pages.maintenance_since().is_none());
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use super::pools::Pools;
+use crate::domain::{Endpoint, WindowStats};
+
+/// Events a slow subscriber may fall behind by before it starts missing some.
+const EVENT_BUFFER: usize = 256;
+
+/// Something that happened to the endpoints, pushed to `/admin/events` subscribers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RouterEvent {
+    EndpointAdded {
+        pool: String,
+        endpoint: String,
+    },
+    EndpointRemoved {
+        pool: String,
+        endpoint: String,
+    },
+    /// Sent when any of these change, and once per endpoint when a subscriber connects.
+    EndpointState {
+        pool: String,
+        endpoint: String,
+        active: bool,
+        draining: bool,
+        weight: usize,
+    },
+    /// A health check deactivated the endpoint, or an active check reactivated it.
+    Health {
+        pool: String,
+        endpoint: String,
+        healthy: bool,
+        success_count: usize,
+        failure_count: usize,
+    },
+    /// Endpoints whose traffic, load or rolling windows changed since the previous stats
+    /// event.
+    Stats {
+        endpoints: Vec<StatsDelta>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatsDelta {
+    pub pool: String,
+    pub endpoint: String,
+    /// Responses and failures since the previous stats event.
+    pub requests: usize,
+    pub failures: usize,
+    pub in_flight: usize,
+    pub request_rate_1m: f64,
+    pub error_rate_1m: f64,
+    pub p99_ms_1m: Option<f64>,
+    /// Every window, as `/printstats` reports them.
+    pub windows: Vec<WindowStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_percentage: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_percentage: Option<f64>,
+}
+
+impl RouterEvent {
+    /// SSE event name, the same as the `type` field.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RouterEvent::EndpointAdded { .. } => "endpoint_added",
+            RouterEvent::EndpointRemoved { .. } => "endpoint_removed",
+            RouterEvent::EndpointState { .. } => "endpoint_state",
+            RouterEvent::Health { .. } => "health",
+            RouterEvent::Stats { .. } => "stats",
+        }
+    }
+
+    pub fn endpoint_state(pool: &str, endpoint: &Endpoint) -> Self {
+        RouterEvent::EndpointState {
+            pool: pool.to_string(),
+            endpoint: endpoint.uri.to_string(),
+            active: endpoint
+                .active_server
+                .load(std::sync::atomic::Ordering::Relaxed),
+            draining: endpoint.is_draining(),
+            weight: endpoint.weight(),
+        }
+    }
+}
+
+/// Fan-out of [`RouterEvent`]s to every connected subscriber.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<RouterEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+}
+
+impl EventBus {
+    /// Dropped when nobody is subscribed.
+    pub fn publish(&self, event: RouterEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RouterEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// Compares the endpoints every `interval` and publishes what changed: endpoints added
+/// or removed, state changes, and a stats delta for endpoints that saw traffic.
+pub struct EventPublisher {
+    pools: Arc<Pools>,
+    events: EventBus,
+    interval: Duration,
+}
+
+/// What the publisher remembers about an endpoint between ticks.
+#[derive(Debug, Clone, PartialEq)]
+struct Observed {
+    active: bool,
+    draining: bool,
+    weight: usize,
+    success_count: usize,
+    failure_count: usize,
+    in_flight: usize,
+    sampled_at: Option<Instant>,
+    windows: Vec<WindowStats>,
+}
+
+impl EventPublisher {
+    pub fn new(pools: Arc<Pools>, events: EventBus, interval: Duration) -> Self {
+        Self {
+            pools,
+            events,
+            interval,
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(self) {
+        let mut previous = HashMap::new();
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            let endpoints = self.pools.all_endpoints().await;
+            for event in diff(&mut previous, &endpoints) {
+                self.events.publish(event);
+            }
+        }
+    }
+}
+
+/// Pool and endpoint URI.
+type EndpointKey = (String, String);
+
+/// Events describing how `endpoints` differ from `previous`, which is then updated.
+fn diff(
+    previous: &mut HashMap<EndpointKey, Observed>,
+    endpoints: &[(String, Endpoint)],
+) -> Vec<RouterEvent> {
+    let mut events = Vec::new();
+    let mut stats = Vec::new();
+    let mut current = HashMap::new();
+
+    for (pool, endpoint) in endpoints {
+        let key = (pool.clone(), endpoint.uri.to_string());
+        let resources = endpoint.resources();
+        let observed = Observed {
+            active: endpoint
+                .active_server
+                .load(std::sync::atomic::Ordering::Relaxed),
+            draining: endpoint.is_draining(),
+            weight: endpoint.weight(),
+            success_count: endpoint.success_count(),
+            failure_count: endpoint.failure_count(),
+            in_flight: endpoint.concurrent_connection_count(),
+            sampled_at: resources.map(|sample| sample.sampled_at),
+            windows: endpoint.request_windows(),
+        };
+
+        let before = previous.get(&key);
+        match before {
+            None => {
+                events.push(RouterEvent::EndpointAdded {
+                    pool: pool.clone(),
+                    endpoint: key.1.clone(),
+                });
+                events.push(RouterEvent::endpoint_state(pool, endpoint));
+            }
+            Some(before)
+                if (before.active, before.draining, before.weight)
+                    != (observed.active, observed.draining, observed.weight) =>
+            {
+                events.push(RouterEvent::endpoint_state(pool, endpoint));
+            }
+            _ => {}
+        }
+
+        let (success_before, failure_before, in_flight_before, sampled_before) = before
+            .map(|b| (b.success_count, b.failure_count, b.in_flight, b.sampled_at))
+            .unwrap_or_default();
+        let requests = (observed.success_count + observed.failure_count)
+            .saturating_sub(success_before + failure_before);
+        // windows keep changing after traffic stops, until it has aged out of them
+        if requests > 0
+            || observed.in_flight != in_flight_before
+            || observed.sampled_at != sampled_before
+            || before.is_some_and(|b| b.windows != observed.windows)
+        {
+            let window = observed.windows.first();
+            stats.push(StatsDelta {
+                pool: pool.clone(),
+                endpoint: key.1.clone(),
+                requests,
+                failures: observed.failure_count.saturating_sub(failure_before),
+                in_flight: observed.in_flight,
+                request_rate_1m: window.as_ref().map_or(0.0, |w| w.request_rate),
+                error_rate_1m: window.as_ref().map_or(0.0, |w| w.error_rate),
+                p99_ms_1m: window.and_then(|w| w.p99_ms),
+                windows: observed.windows.clone(),
+                cpu_percentage: resources.map(|sample| sample.cpu_percentage),
+                memory_percentage: resources.map(|sample| sample.memory_percentage),
+            });
+        }
+
+        current.insert(key, observed);
+    }
+
+    for (pool, uri) in previous.keys() {
+        if !current.contains_key(&(pool.clone(), uri.clone())) {
+            events.push(RouterEvent::EndpointRemoved {
+                pool: pool.clone(),
+                endpoint: uri.clone(),
+            });
+        }
+    }
+    if !stats.is_empty() {
+        events.push(RouterEvent::Stats { endpoints: stats });
+    }
+
+    *previous = current;
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Uri;
+
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_changes_only() {
+        let mut previous = HashMap::new();
+        let endpoint = |uri| ("api".to_string(), Endpoint::new(Uri::from_static(uri)));
+        let first = endpoint("http://localhost:7001");
+        let second = endpoint("http://localhost:7002");
+
+        let events = diff(&mut previous, &[first.clone(), second.clone()]);
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].name(), "endpoint_added");
+        assert_eq!(events[1].name(), "endpoint_state");
+
+        // nothing happened in between
+        assert!(diff(&mut previous, &[first.clone(), second.clone()]).is_empty());
+
+        first.1.drain();
+        first.1.incr_success();
+        first.1.incr_failure();
+        first.1.record_request(Duration::from_millis(10), true);
+        let events = diff(&mut previous, std::slice::from_ref(&first));
+        assert_eq!(
+            events[0],
+            RouterEvent::EndpointState {
+                pool: "api".to_string(),
+                endpoint: "http://localhost:7001/".to_string(),
+                active: true,
+                draining: true,
+                weight: 1,
+            }
+        );
+        assert_eq!(
+            events[1],
+            RouterEvent::EndpointRemoved {
+                pool: "api".to_string(),
+                endpoint: "http://localhost:7002/".to_string(),
+            }
+        );
+        let RouterEvent::Stats { endpoints } = &events[2] else {
+            panic!("expected stats, got {:?}", events[2]);
+        };
+        assert_eq!((endpoints[0].requests, endpoints[0].failures), (2, 1));
+        assert_eq!(endpoints[0].error_rate_1m, 1.0);
+        assert_eq!(endpoints[0].windows.len(), 3);
+        assert_eq!(endpoints[0].windows[2].requests, 1);
+
+        let json = serde_json::to_value(&events[1]).unwrap();
+        assert_eq!(json["type"], "endpoint_removed");
+    }
+}
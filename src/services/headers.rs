@@ -0,0 +1,309 @@
+use std::fmt;
+
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+
+/// Headers that describe one connection rather than the request, so they are never
+/// forwarded in either direction.
+const HOP_BY_HOP: [HeaderName; 8] = [
+    header::CONNECTION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+    HeaderName::from_static("keep-alive"),
+];
+
+/// `headers` without hop-by-hop headers, those listed in `Connection`, and `skip`.
+pub fn forwardable(headers: &HeaderMap, skip: &[HeaderName]) -> HeaderMap {
+    let listed: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    let mut forwarded = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        if !HOP_BY_HOP.contains(name) && !listed.contains(name) && !skip.contains(name) {
+            forwarded.append(name.clone(), value.clone());
+        }
+    }
+    forwarded
+}
+
+/// Request details a header [`Template`] can refer to.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TemplateVars<'a> {
+    pub client_ip: Option<std::net::IpAddr>,
+    pub request_id: &'a str,
+    pub host: Option<&'a str>,
+    pub method: &'a str,
+    pub path: &'a str,
+    /// Name of the matched route, empty for the default pool.
+    pub route: &'a str,
+    pub pool: &'a str,
+    /// URI of the endpoint the request went to.
+    pub upstream: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variable {
+    ClientIp,
+    RequestId,
+    Host,
+    Method,
+    Path,
+    Route,
+    Pool,
+    Upstream,
+}
+
+impl Variable {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "client_ip" => Variable::ClientIp,
+            "request_id" => Variable::RequestId,
+            "host" => Variable::Host,
+            "method" => Variable::Method,
+            "path" => Variable::Path,
+            "route" => Variable::Route,
+            "pool" => Variable::Pool,
+            "upstream" => Variable::Upstream,
+            _ => return None,
+        })
+    }
+
+    fn render(self, vars: &TemplateVars, out: &mut String) {
+        match self {
+            Variable::ClientIp => {
+                if let Some(ip) = vars.client_ip {
+                    out.push_str(&ip.to_string());
+                }
+            }
+            Variable::RequestId => out.push_str(vars.request_id),
+            Variable::Host => out.push_str(vars.host.unwrap_or_default()),
+            Variable::Method => out.push_str(vars.method),
+            Variable::Path => out.push_str(vars.path),
+            Variable::Route => out.push_str(vars.route),
+            Variable::Pool => out.push_str(vars.pool),
+            Variable::Upstream => out.push_str(vars.upstream.unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Variable(Variable),
+}
+
+/// A header value with `{variable}` placeholders, see [`TemplateVars`]; `{{` and `}}`
+/// stand for literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError(String);
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(TemplateError(format!("unmatched {{ in {}", template)))
+                            }
+                        }
+                    }
+                    let variable = Variable::parse(name.trim()).ok_or_else(|| {
+                        TemplateError(format!("unknown variable {{{}}} in {}", name, template))
+                    })?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Variable(variable));
+                }
+                '}' => return Err(TemplateError(format!("unmatched }} in {}", template))),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, vars: &TemplateVars) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => out.push_str(literal),
+                Part::Variable(variable) => variable.render(vars, &mut out),
+            }
+        }
+        out
+    }
+}
+
+/// Changes to a set of headers, applied in field order: remove, rename, set, add.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderPolicy {
+    pub remove: Vec<HeaderName>,
+    /// Moves every value of the first header to the second, replacing any there.
+    pub rename: Vec<(HeaderName, HeaderName)>,
+    /// Replaces any existing values.
+    pub set: Vec<(HeaderName, Template)>,
+    /// Appends to any existing values.
+    pub add: Vec<(HeaderName, Template)>,
+}
+
+impl HeaderPolicy {
+    /// Values that render to something that is not a valid header value are skipped.
+    pub fn apply(&self, headers: &mut HeaderMap, vars: &TemplateVars) {
+        for name in &self.remove {
+            headers.remove(name);
+        }
+        for (from, to) in &self.rename {
+            if let header::Entry::Occupied(entry) = headers.entry(from) {
+                let values: Vec<HeaderValue> = entry.remove_entry_mult().1.collect();
+                headers.remove(to);
+                for value in values {
+                    headers.append(to, value);
+                }
+            }
+        }
+        let render = |name: &HeaderName, template: &Template| {
+            let value = template.render(vars);
+            let parsed = HeaderValue::from_str(&value).ok();
+            if parsed.is_none() {
+                tracing::debug!(header = %name, value, "skipping invalid header value");
+            }
+            parsed
+        };
+        for (name, template) in &self.set {
+            match render(name, template) {
+                Some(value) => {
+                    headers.insert(name, value);
+                }
+                None => {
+                    headers.remove(name);
+                }
+            }
+        }
+        for (name, template) in &self.add {
+            if let Some(value) = render(name, template) {
+                headers.append(name, value);
+            }
+        }
+    }
+}
+
+/// Header changes on the way to the backend and on the way back.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderPolicies {
+    pub request: HeaderPolicy,
+    pub response: HeaderPolicy,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template() {
+        let vars = TemplateVars {
+            client_ip: Some("10.0.0.7".parse().unwrap()),
+            route: "api",
+            upstream: Some("http://api-1:8080/"),
+            ..Default::default()
+        };
+        let render = |template| Template::parse(template).unwrap().render(&vars);
+
+        assert_eq!(
+            render("for={client_ip}; route={ route }"),
+            "for=10.0.0.7; route=api"
+        );
+        assert_eq!(render("{upstream}"), "http://api-1:8080/");
+        assert_eq!(render("{{literal}} {host}"), "{literal} ");
+        assert!(Template::parse("{nope}").is_err());
+        assert!(Template::parse("a}b").is_err());
+        assert!(Template::parse("{host").is_err());
+    }
+
+    #[test]
+    fn test_policy() {
+        let name = HeaderName::from_static;
+        let mut headers = HeaderMap::new();
+        headers.insert(header::SERVER, HeaderValue::from_static("nginx"));
+        headers.append("x-old", HeaderValue::from_static("1"));
+        headers.append("x-old", HeaderValue::from_static("2"));
+        headers.insert("x-new", HeaderValue::from_static("gone"));
+        headers.insert("x-route", HeaderValue::from_static("client"));
+        headers.insert("x-seen", HeaderValue::from_static("a"));
+
+        let policy = HeaderPolicy {
+            remove: vec![header::SERVER],
+            rename: vec![(name("x-old"), name("x-new"))],
+            set: vec![(name("x-route"), Template::parse("{route}").unwrap())],
+            add: vec![(name("x-seen"), Template::parse("b").unwrap())],
+        };
+        let vars = TemplateVars {
+            route: "api",
+            ..Default::default()
+        };
+        policy.apply(&mut headers, &vars);
+
+        assert!(!headers.contains_key(header::SERVER));
+        assert!(!headers.contains_key("x-old"));
+        let all = |name| {
+            headers
+                .get_all(name)
+                .iter()
+                .map(|v| v.to_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(all("x-new"), ["1", "2"]);
+        assert_eq!(all("x-route"), ["api"]);
+        assert_eq!(all("x-seen"), ["a", "b"]);
+
+        let mut hop = HeaderMap::new();
+        hop.insert(
+            header::CONNECTION,
+            HeaderValue::from_static("close, x-private"),
+        );
+        hop.insert("x-private", HeaderValue::from_static("1"));
+        hop.insert(header::HOST, HeaderValue::from_static("example.com"));
+        hop.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        let forwarded = forwardable(&hop, &[header::HOST]);
+        assert_eq!(forwarded.len(), 1);
+        assert!(forwarded.contains_key(header::ACCEPT));
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use futures_util::future::join_all;
+use tokio::task::JoinHandle;
+
+use super::{
+    events::{EventBus, RouterEvent},
+    metrics::Metrics,
+    pools::HealthCheckSettings,
+    routing::upstream_url,
+};
+use crate::{app_state::EndpointStoreType, domain::Endpoint};
+
+/// Actively probes every endpoint in one pool, see [`HealthCheckSettings`].
+pub struct HealthChecker {
+    pool: String,
+    store: EndpointStoreType,
+    settings: HealthCheckSettings,
+    client: reqwest::Client,
+    metrics: Option<Arc<Metrics>>,
+    events: Option<EventBus>,
+    streaks: HashMap<String, Streak>,
+    /// Endpoints this checker deactivated; only these are reactivated when they pass again,
+    /// so an endpoint disabled through the admin API stays disabled.
+    ejected: HashSet<String>,
+}
+
+/// Consecutive probe results of one endpoint; only one side is non-zero at a time.
+#[derive(Debug, Default, Clone, Copy)]
+struct Streak {
+    passes: u32,
+    failures: u32,
+}
+
+impl HealthChecker {
+    pub fn new(pool: &str, store: EndpointStoreType, settings: HealthCheckSettings) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(settings.timeout)
+            .build()
+            .expect("Failed to build health check client");
+        Self {
+            pool: pool.to_string(),
+            store,
+            settings,
+            client,
+            metrics: None,
+            events: None,
+            streaks: HashMap::new(),
+            ejected: HashSet::new(),
+        }
+    }
+
+    pub fn with_metrics(self, metrics: Arc<Metrics>) -> Self {
+        Self {
+            metrics: Some(metrics),
+            ..self
+        }
+    }
+
+    pub fn with_events(self, events: EventBus) -> Self {
+        Self {
+            events: Some(events),
+            ..self
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        let mut ticker = tokio::time::interval(self.settings.interval);
+        loop {
+            ticker.tick().await;
+            self.check_all().await;
+        }
+    }
+
+    pub async fn check_all(&mut self) {
+        let endpoints = match self.store.read().await.get_all_endpoints().await {
+            Ok(endpoints) => endpoints,
+            Err(e) => {
+                tracing::warn!(pool = %self.pool, error = ?e, "health checker failed to read endpoints");
+                return;
+            }
+        };
+
+        let results = join_all(endpoints.iter().map(|endpoint| self.probe(endpoint))).await;
+
+        self.streaks
+            .retain(|uri, _| endpoints.iter().any(|ep| &ep.uri.to_string() == uri));
+        self.ejected
+            .retain(|uri| endpoints.iter().any(|ep| &ep.uri.to_string() == uri));
+        for (endpoint, passed) in endpoints.iter().zip(results) {
+            self.record(endpoint, passed);
+        }
+    }
+
+    async fn probe(&self, endpoint: &Endpoint) -> bool {
+        let url = upstream_url(&endpoint.uri, &self.settings.path, None);
+        match self.client.get(&url).send().await {
+            Ok(response) => {
+                let passed = response.status().is_success() || response.status().is_redirection();
+                if !passed {
+                    tracing::debug!(pool = %self.pool, url, status = response.status().as_u16(), "health check failed");
+                }
+                passed
+            }
+            Err(e) => {
+                tracing::debug!(pool = %self.pool, url, error = %e, "health check failed");
+                false
+            }
+        }
+    }
+
+    fn record(&mut self, endpoint: &Endpoint, passed: bool) {
+        let uri = endpoint.uri.to_string();
+        let streak = self.streaks.entry(uri.clone()).or_default();
+        if passed {
+            *streak = Streak {
+                passes: streak.passes.saturating_add(1),
+                failures: 0,
+            };
+        } else {
+            *streak = Streak {
+                passes: 0,
+                failures: streak.failures.saturating_add(1),
+            };
+        }
+        let streak = *streak;
+
+        let active = endpoint
+            .active_server
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if active {
+            // re-enabled by someone else since we ejected it
+            self.ejected.remove(&uri);
+        }
+        let ejected = active && streak.failures >= self.settings.unhealthy_threshold;
+        let restored = !active
+            && self.ejected.contains(&uri)
+            && streak.passes >= self.settings.healthy_threshold;
+
+        if ejected {
+            self.ejected.insert(uri);
+            endpoint.deactivate();
+            tracing::warn!(pool = %self.pool, endpoint = %endpoint.uri, failures = streak.failures, "health check deactivated endpoint");
+        } else if restored {
+            self.ejected.remove(&uri);
+            endpoint.activate();
+            tracing::info!(pool = %self.pool, endpoint = %endpoint.uri, passes = streak.passes, "health check reactivated endpoint");
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_health_check(&self.pool, endpoint, passed, ejected);
+        }
+        if let (Some(events), true) = (&self.events, ejected || restored) {
+            events.publish(RouterEvent::Health {
+                pool: self.pool.clone(),
+                endpoint: endpoint.uri.to_string(),
+                healthy: restored,
+                success_count: endpoint.success_count(),
+                failure_count: endpoint.failure_count(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    use axum::{extract::State, http::StatusCode, routing::get, Router};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::services::btreemap_endpoint_store::BTreeMapEndpointStore;
+
+    #[tokio::test]
+    async fn test_thresholds() {
+        let up = Arc::new(AtomicBool::new(false));
+        let backend = Router::new()
+            .route(
+                "/healthz",
+                get(|State(up): State<Arc<AtomicBool>>| async move {
+                    if up.load(Ordering::SeqCst) {
+                        StatusCode::OK
+                    } else {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    }
+                }),
+            )
+            .with_state(up.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, backend).await });
+
+        let endpoint = Endpoint::new(uri.parse().unwrap());
+        let mut store = BTreeMapEndpointStore::default();
+        crate::domain::EndpointStore::add_endpoint(&mut store, endpoint.clone())
+            .await
+            .unwrap();
+        let mut checker = HealthChecker::new(
+            "api",
+            Arc::new(RwLock::new(store)),
+            HealthCheckSettings {
+                path: "/healthz".to_string(),
+                interval: Duration::from_secs(1),
+                timeout: Duration::from_secs(1),
+                healthy_threshold: 2,
+                unhealthy_threshold: 2,
+            },
+        );
+        let active = || endpoint.active_server.load(Ordering::Relaxed);
+
+        checker.check_all().await;
+        assert!(active(), "one failure is below the threshold");
+        checker.check_all().await;
+        assert!(!active());
+
+        up.store(true, Ordering::SeqCst);
+        checker.check_all().await;
+        assert!(!active());
+        checker.check_all().await;
+        assert!(active());
+
+        // disabled through the admin API: passing checks must not bring it back
+        endpoint.deactivate();
+        checker.check_all().await;
+        checker.check_all().await;
+        checker.check_all().await;
+        assert!(!active());
+    }
+}
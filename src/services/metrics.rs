@@ -0,0 +1,463 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::domain::{Endpoint, HealthCheckResult};
+
+/// Prometheus metrics for proxied traffic and endpoint state.
+///
+/// Counters and histograms are updated as requests complete; the per-endpoint gauges
+/// and rolling window summaries are built from the endpoint stores on every scrape.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    retries: IntCounterVec,
+    health_checks: IntCounterVec,
+    ejections: IntCounterVec,
+    split_requests: IntCounterVec,
+    split_request_duration: HistogramVec,
+    mirror_responses: IntCounterVec,
+    mirror_mismatches: IntCounterVec,
+    mirror_latency: HistogramVec,
+    mirror_dropped: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("router".to_string()), None)?;
+
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "requests_total",
+                "Proxied requests by endpoint and upstream status class",
+            ),
+            &["pool", "endpoint", "status_class"],
+        )?;
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "request_duration_seconds",
+                "Time from forwarding a request to receiving the upstream response",
+            ),
+            &["pool", "endpoint"],
+        )?;
+        let retries = IntCounterVec::new(
+            Opts::new(
+                "retries_total",
+                "Requests retried on another endpoint after this one failed",
+            ),
+            &["pool", "endpoint"],
+        )?;
+        let health_checks = IntCounterVec::new(
+            Opts::new("health_checks_total", "Endpoint health checks by result"),
+            &["pool", "endpoint", "result"],
+        )?;
+        let ejections = IntCounterVec::new(
+            Opts::new(
+                "ejections_total",
+                "Endpoints deactivated by a failed health check",
+            ),
+            &["pool", "endpoint"],
+        )?;
+        let split_requests = IntCounterVec::new(
+            Opts::new(
+                "split_requests_total",
+                "Requests through a traffic split by route, chosen pool and status class",
+            ),
+            &["route", "pool", "status_class"],
+        )?;
+        let split_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "split_request_duration_seconds",
+                "Time to answer requests through a traffic split, retries included",
+            ),
+            &["route", "pool"],
+        )?;
+        let mirror_responses = IntCounterVec::new(
+            Opts::new(
+                "mirror_responses_total",
+                "Mirrored requests by the status class of the primary and shadow responses",
+            ),
+            &["route", "primary_status_class", "shadow_status_class"],
+        )?;
+        let mirror_mismatches = IntCounterVec::new(
+            Opts::new(
+                "mirror_status_mismatches_total",
+                "Mirrored requests whose shadow status differed from the primary one",
+            ),
+            &["route"],
+        )?;
+        let mirror_latency = HistogramVec::new(
+            HistogramOpts::new(
+                "mirror_latency_seconds",
+                "Latency of mirrored requests on the primary and shadow side",
+            ),
+            &["route", "side"],
+        )?;
+        let mirror_dropped = IntCounterVec::new(
+            Opts::new(
+                "mirror_dropped_total",
+                "Mirrored requests dropped because MIRROR_MAX_IN_FLIGHT copies were in flight",
+            ),
+            &["route"],
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(request_duration.clone()))?;
+        registry.register(Box::new(retries.clone()))?;
+        registry.register(Box::new(health_checks.clone()))?;
+        registry.register(Box::new(ejections.clone()))?;
+        registry.register(Box::new(split_requests.clone()))?;
+        registry.register(Box::new(split_request_duration.clone()))?;
+        registry.register(Box::new(mirror_responses.clone()))?;
+        registry.register(Box::new(mirror_mismatches.clone()))?;
+        registry.register(Box::new(mirror_latency.clone()))?;
+        registry.register(Box::new(mirror_dropped.clone()))?;
+        registry.register(Box::new(
+            prometheus::process_collector::ProcessCollector::for_self(),
+        ))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            request_duration,
+            retries,
+            health_checks,
+            ejections,
+            split_requests,
+            split_request_duration,
+            mirror_responses,
+            mirror_mismatches,
+            mirror_latency,
+            mirror_dropped,
+        })
+    }
+
+    /// Record a completed upstream exchange; `status` is `None` when no response came back.
+    pub fn observe_request(
+        &self,
+        pool: &str,
+        endpoint: &Endpoint,
+        status: Option<StatusCode>,
+        elapsed: Duration,
+    ) {
+        let endpoint = endpoint.uri.to_string();
+        self.requests
+            .with_label_values(&[pool, &endpoint, status_class(status)])
+            .inc();
+        self.request_duration
+            .with_label_values(&[pool, &endpoint])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Outcome of a request whose pool a traffic split chose; `status` is `None` when no
+    /// endpoint answered.
+    pub fn observe_split(
+        &self,
+        route: &str,
+        pool: &str,
+        status: Option<StatusCode>,
+        elapsed: Duration,
+    ) {
+        self.split_requests
+            .with_label_values(&[route, pool, status_class(status)])
+            .inc();
+        self.split_request_duration
+            .with_label_values(&[route, pool])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Both sides of a mirrored request. `primary` is `None` if its outcome never arrived,
+    /// and each status is `None` when no response came back.
+    pub fn observe_mirror(
+        &self,
+        route: &str,
+        primary: Option<(Option<StatusCode>, Duration)>,
+        shadow: (Option<StatusCode>, Duration),
+    ) {
+        let primary_class = match primary {
+            Some((status, _)) => status_class(status),
+            None => "unknown",
+        };
+        self.mirror_responses
+            .with_label_values(&[route, primary_class, status_class(shadow.0)])
+            .inc();
+        if let Some((status, latency)) = primary {
+            if status != shadow.0 {
+                self.mirror_mismatches.with_label_values(&[route]).inc();
+            }
+            self.mirror_latency
+                .with_label_values(&[route, "primary"])
+                .observe(latency.as_secs_f64());
+        }
+        self.mirror_latency
+            .with_label_values(&[route, "shadow"])
+            .observe(shadow.1.as_secs_f64());
+    }
+
+    /// A copy not sent because the limit of mirrored requests in flight was reached.
+    pub fn observe_mirror_dropped(&self, route: &str) {
+        self.mirror_dropped.with_label_values(&[route]).inc();
+    }
+
+    pub fn observe_retry(&self, pool: &str, endpoint: &Endpoint) {
+        self.retries
+            .with_label_values(&[pool, &endpoint.uri.to_string()])
+            .inc();
+    }
+
+    /// Endpoints the failure-ratio check on proxied traffic just deactivated. That check is
+    /// no probe and runs on every request, so only its ejections are counted.
+    pub fn observe_ejections(&self, pool: &str, results: &[HealthCheckResult]) {
+        for result in results.iter().filter(|result| !result.healthy) {
+            self.ejections
+                .with_label_values(&[pool, &result.endpoint.uri.to_string()])
+                .inc();
+        }
+    }
+
+    /// One active health check probe; `ejected` if it deactivated the endpoint.
+    pub fn observe_health_check(
+        &self,
+        pool: &str,
+        endpoint: &Endpoint,
+        healthy: bool,
+        ejected: bool,
+    ) {
+        let endpoint = endpoint.uri.to_string();
+        let outcome = if healthy { "healthy" } else { "unhealthy" };
+        self.health_checks
+            .with_label_values(&[pool, &endpoint, outcome])
+            .inc();
+        if ejected {
+            self.ejections.with_label_values(&[pool, &endpoint]).inc();
+        }
+    }
+
+    /// Render every metric in the text exposition format, with gauges taken from `endpoints`
+    /// and the names of their pools, and the access log lines dropped so far.
+    pub fn render(
+        &self,
+        endpoints: &[(String, Endpoint)],
+        access_log_dropped: u64,
+    ) -> Result<String, prometheus::Error> {
+        // a fresh set per scrape, so removed endpoints do not linger and concurrent scrapes
+        // never see each other's half-filled gauges
+        let gauges = EndpointGauges::new()?;
+        for (pool, endpoint) in endpoints {
+            gauges.set(pool, endpoint);
+        }
+        let dropped = IntCounter::new(
+            "access_log_dropped_lines_total",
+            "Access log lines dropped because the sink fell behind",
+        )?;
+        gauges.registry.register(Box::new(dropped.clone()))?;
+        dropped.inc_by(access_log_dropped);
+
+        let mut families = self.registry.gather();
+        families.extend(gauges.registry.gather());
+        families.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&families, &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+/// Per-endpoint state read at scrape time.
+struct EndpointGauges {
+    registry: Registry,
+    in_flight: IntGaugeVec,
+    active: IntGaugeVec,
+    draining: IntGaugeVec,
+    window_request_rate: GaugeVec,
+    window_error_rate: GaugeVec,
+    window_latency: GaugeVec,
+}
+
+impl EndpointGauges {
+    fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("router".to_string()), None)?;
+
+        let in_flight = IntGaugeVec::new(
+            Opts::new("in_flight_requests", "Requests currently being proxied"),
+            &["pool", "endpoint"],
+        )?;
+        let active = IntGaugeVec::new(
+            Opts::new("endpoint_active", "1 if the endpoint is active, 0 if not"),
+            &["pool", "endpoint"],
+        )?;
+        let draining = IntGaugeVec::new(
+            Opts::new("endpoint_draining", "1 if the endpoint is being drained"),
+            &["pool", "endpoint"],
+        )?;
+        let window_request_rate = GaugeVec::new(
+            Opts::new(
+                "window_request_rate",
+                "Requests per second over the trailing window",
+            ),
+            &["pool", "endpoint", "window"],
+        )?;
+        let window_error_rate = GaugeVec::new(
+            Opts::new(
+                "window_error_rate",
+                "Share of requests over the trailing window that failed or returned 5xx",
+            ),
+            &["pool", "endpoint", "window"],
+        )?;
+        let window_latency = GaugeVec::new(
+            Opts::new(
+                "window_latency_seconds",
+                "Upstream latency percentiles over the trailing window",
+            ),
+            &["pool", "endpoint", "window", "quantile"],
+        )?;
+
+        registry.register(Box::new(in_flight.clone()))?;
+        registry.register(Box::new(active.clone()))?;
+        registry.register(Box::new(draining.clone()))?;
+        registry.register(Box::new(window_request_rate.clone()))?;
+        registry.register(Box::new(window_error_rate.clone()))?;
+        registry.register(Box::new(window_latency.clone()))?;
+
+        Ok(Self {
+            registry,
+            in_flight,
+            active,
+            draining,
+            window_request_rate,
+            window_error_rate,
+            window_latency,
+        })
+    }
+
+    fn set(&self, pool: &str, endpoint: &Endpoint) {
+        let label = endpoint.uri.to_string();
+        self.in_flight
+            .with_label_values(&[pool, &label])
+            .set(endpoint.concurrent_connection_count() as i64);
+        self.active.with_label_values(&[pool, &label]).set(
+            endpoint
+                .active_server
+                .load(std::sync::atomic::Ordering::Relaxed) as i64,
+        );
+        self.draining
+            .with_label_values(&[pool, &label])
+            .set(endpoint.is_draining() as i64);
+
+        for window in endpoint.request_windows() {
+            self.window_request_rate
+                .with_label_values(&[pool, &label, window.window])
+                .set(window.request_rate);
+            self.window_error_rate
+                .with_label_values(&[pool, &label, window.window])
+                .set(window.error_rate);
+            for (quantile, value) in [
+                ("0.5", window.p50_ms),
+                ("0.9", window.p90_ms),
+                ("0.99", window.p99_ms),
+            ] {
+                if let Some(ms) = value {
+                    self.window_latency
+                        .with_label_values(&[pool, &label, window.window, quantile])
+                        .set(ms / 1000.0);
+                }
+            }
+        }
+    }
+}
+
+fn status_class(status: Option<StatusCode>) -> &'static str {
+    match status.map(|status| status.as_u16() / 100) {
+        Some(1) => "1xx",
+        Some(2) => "2xx",
+        Some(3) => "3xx",
+        Some(4) => "4xx",
+        Some(5) => "5xx",
+        _ => "error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Uri;
+
+    use super::*;
+
+    #[test]
+    fn test_render_exposition_format() {
+        let metrics = Metrics::new().unwrap();
+        let endpoint = Endpoint::new(Uri::from_static("http://localhost:7001"));
+        endpoint.increase_concurrent_connection_count();
+        endpoint.record_request(Duration::from_millis(20), false);
+
+        metrics.observe_request(
+            "web",
+            &endpoint,
+            Some(StatusCode::NOT_FOUND),
+            Duration::from_millis(20),
+        );
+        metrics.observe_request("web", &endpoint, None, Duration::from_millis(5));
+        // the same URI in another pool is a series of its own
+        metrics.observe_request("api", &endpoint, None, Duration::from_millis(5));
+        metrics.observe_retry("web", &endpoint);
+        metrics.observe_split(
+            "checkout",
+            "canary",
+            Some(StatusCode::BAD_GATEWAY),
+            Duration::from_millis(30),
+        );
+        metrics.observe_health_check("web", &endpoint, false, true);
+        metrics.observe_ejections(
+            "web",
+            &[
+                HealthCheckResult {
+                    endpoint: endpoint.clone(),
+                    healthy: true,
+                },
+                HealthCheckResult {
+                    endpoint: endpoint.clone(),
+                    healthy: false,
+                },
+            ],
+        );
+
+        let text = metrics.render(&[("web".to_string(), endpoint)], 0).unwrap();
+
+        for line in [
+            r#"router_requests_total{endpoint="http://localhost:7001/",pool="web",status_class="4xx"} 1"#,
+            r#"router_requests_total{endpoint="http://localhost:7001/",pool="web",status_class="error"} 1"#,
+            r#"router_request_duration_seconds_count{endpoint="http://localhost:7001/",pool="web"} 2"#,
+            r#"router_requests_total{endpoint="http://localhost:7001/",pool="api",status_class="error"} 1"#,
+            r#"router_retries_total{endpoint="http://localhost:7001/",pool="web"} 1"#,
+            r#"router_health_checks_total{endpoint="http://localhost:7001/",pool="web",result="unhealthy"} 1"#,
+            r#"router_ejections_total{endpoint="http://localhost:7001/",pool="web"} 2"#,
+            r#"router_in_flight_requests{endpoint="http://localhost:7001/",pool="web"} 1"#,
+            r#"router_endpoint_active{endpoint="http://localhost:7001/",pool="web"} 1"#,
+            r#"router_endpoint_draining{endpoint="http://localhost:7001/",pool="web"} 0"#,
+            r#"router_window_error_rate{endpoint="http://localhost:7001/",pool="web",window="5m"} 0"#,
+            r#"router_window_request_rate{endpoint="http://localhost:7001/",pool="web",window="1m"} 0.016"#,
+            r#"router_window_latency_seconds{endpoint="http://localhost:7001/",pool="web",quantile="0.99",window="15m"} 0.02"#,
+            r#"router_split_requests_total{pool="canary",route="checkout",status_class="5xx"} 1"#,
+        ] {
+            assert!(text.contains(line), "missing {} in\n{}", line, text);
+        }
+        // passive checks on proxied traffic are not probes
+        assert!(!text.contains(r#"result="healthy""#));
+    }
+
+    #[test]
+    fn test_render_drops_removed_endpoints() {
+        let metrics = Metrics::new().unwrap();
+        let endpoint = Endpoint::new(Uri::from_static("http://localhost:7001"));
+
+        let text = metrics.render(&[("web".to_string(), endpoint)], 0).unwrap();
+        assert!(text
+            .contains(r#"router_endpoint_active{endpoint="http://localhost:7001/",pool="web"} 1"#));
+        let text = metrics.render(&[], 0).unwrap();
+        assert!(!text.contains("router_endpoint_active"), "{}", text);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use axum::{body::Bytes, http::StatusCode};
+use tokio::{
+    sync::{oneshot, Semaphore},
+    task::JoinHandle,
+};
+
+use super::{metrics::Metrics, pools::Pool, routing::upstream_url};
+use crate::utils::constants::MIRROR_MAX_IN_FLIGHT;
+
+/// Copies of a route's requests sent to a shadow pool, see [`spawn`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorSettings {
+    pub pool: String,
+    /// Share of requests copied, from 0 to 100.
+    pub percent: f64,
+    /// Mirrored requests still waiting after this are abandoned.
+    pub timeout: Duration,
+}
+
+impl MirrorSettings {
+    pub fn sample(&self) -> bool {
+        rand::random::<f64>() * 100.0 < self.percent
+    }
+}
+
+/// A request as it was forwarded to the primary pool.
+pub struct MirroredRequest {
+    pub route: String,
+    pub request_id: String,
+    pub method: reqwest::Method,
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: reqwest::header::HeaderMap,
+    pub body: Bytes,
+}
+
+/// How the primary pool answered; `status` is `None` when no endpoint did.
+#[derive(Debug, Clone, Copy)]
+pub struct PrimaryOutcome {
+    pub status: Option<StatusCode>,
+    pub latency: Duration,
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+/// Permits for mirrored requests in flight across every route, `MIRROR_MAX_IN_FLIGHT` of them.
+pub fn in_flight_limit() -> &'static Arc<Semaphore> {
+    static PERMITS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+    PERMITS.get_or_init(|| Arc::new(Semaphore::new(*MIRROR_MAX_IN_FLIGHT)))
+}
+
+/// Send `request` to an endpoint of `pool` in the background, discarding the response.
+///
+/// Once the primary outcome arrives on the returned sender, the two statuses and latencies
+/// are recorded side by side. Nothing here can hold up or fail the primary request: the
+/// caller never waits on the task, and a dropped sender just records the shadow side.
+/// Returns `None`, dropping the copy, when `permits` has none left.
+pub fn spawn(
+    settings: &MirrorSettings,
+    permits: &Arc<Semaphore>,
+    pool: Pool,
+    metrics: Arc<Metrics>,
+    request: MirroredRequest,
+) -> Option<(oneshot::Sender<PrimaryOutcome>, JoinHandle<()>)> {
+    let Ok(permit) = permits.clone().try_acquire_owned() else {
+        tracing::debug!(request_id = %request.request_id, route = %request.route, "too many mirrored requests in flight, dropping copy");
+        metrics.observe_mirror_dropped(&request.route);
+        return None;
+    };
+    let (sender, primary) = oneshot::channel();
+    let timeout = settings.timeout;
+    let handle = tokio::spawn(async move {
+        let _permit = permit;
+        let shadow = send(&pool, &request, timeout).await;
+        let primary: Option<PrimaryOutcome> = tokio::time::timeout(timeout, primary)
+            .await
+            .ok()
+            .and_then(Result::ok);
+        metrics.observe_mirror(
+            &request.route,
+            primary.map(|primary| (primary.status, primary.latency)),
+            shadow,
+        );
+        tracing::debug!(
+            request_id = %request.request_id,
+            route = %request.route,
+            pool = %pool.name,
+            primary_status = primary.and_then(|p| p.status).map(|s| s.as_u16()),
+            shadow_status = shadow.0.map(|s| s.as_u16()),
+            primary_ms = primary.map(|p| p.latency.as_secs_f64() * 1000.0),
+            shadow_ms = shadow.1.as_secs_f64() * 1000.0,
+            "mirrored request finished"
+        );
+    });
+    Some((sender, handle))
+}
+
+/// The shadow's outcome is kept out of the endpoint's counters and request metrics, which
+/// would otherwise let copies trip failure-ratio ejection and skew its stats; it is only
+/// recorded as the shadow side of the comparison. The copy still counts as a connection,
+/// being real load on the endpoint.
+async fn send(
+    pool: &Pool,
+    request: &MirroredRequest,
+    timeout: Duration,
+) -> (Option<StatusCode>, Duration) {
+    let started = Instant::now();
+    let endpoint = match pool.store.read().await.get_next_endpoint().await {
+        Ok(endpoint) => endpoint,
+        Err(_) => return (None, started.elapsed()),
+    };
+
+    endpoint.increase_concurrent_connection_count();
+    let result = client()
+        .request(
+            request.method.clone(),
+            upstream_url(&endpoint.uri, &request.path, request.query.as_deref()),
+        )
+        .headers(request.headers.clone())
+        .body(request.body.clone())
+        .timeout(timeout)
+        .send()
+        .await;
+    endpoint.decrease_concurrent_connection_count();
+    let elapsed = started.elapsed();
+
+    let status = match result {
+        Ok(response) => StatusCode::from_u16(response.status().as_u16()).ok(),
+        Err(e) => {
+            tracing::debug!(endpoint = %endpoint.uri, error = %e, "mirrored request failed");
+            None
+        }
+    };
+    (status, elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::Uri, routing::post, Router};
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{
+        domain::{Endpoint, EndpointStore},
+        services::btreemap_endpoint_store::BTreeMapEndpointStore,
+    };
+
+    #[tokio::test]
+    async fn test_mirror_records_both_sides() {
+        let backend = Router::new().route(
+            "/orders",
+            post(|body: String| async move {
+                assert_eq!(body, "{}");
+                StatusCode::SERVICE_UNAVAILABLE
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri: Uri = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        tokio::spawn(async move { axum::serve(listener, backend).await });
+
+        let endpoint = Endpoint::new(uri);
+        let mut store = BTreeMapEndpointStore::default();
+        store.add_endpoint(endpoint.clone()).await.unwrap();
+        let pool = Pool {
+            name: "shadow".to_string(),
+            store: Arc::new(RwLock::new(store)),
+            health_check: None,
+        };
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let settings = MirrorSettings {
+            pool: "shadow".to_string(),
+            percent: 100.0,
+            timeout: Duration::from_secs(5),
+        };
+        assert!(settings.sample());
+
+        let permits = Arc::new(Semaphore::new(1));
+        let (primary, task) = spawn(&settings, &permits, pool, metrics.clone(), orders()).unwrap();
+        primary
+            .send(PrimaryOutcome {
+                status: Some(StatusCode::OK),
+                latency: Duration::from_millis(10),
+            })
+            .unwrap();
+        task.await.unwrap();
+
+        let text = metrics.render(&[], 0).unwrap();
+        let line = r#"router_mirror_responses_total{primary_status_class="2xx",route="orders",shadow_status_class="5xx"} 1"#;
+        assert!(text.contains(line), "missing {} in\n{}", line, text);
+        assert!(text.contains(r#"router_mirror_status_mismatches_total{route="orders"} 1"#));
+        assert!(
+            text.contains(r#"router_mirror_latency_seconds_count{route="orders",side="shadow"} 1"#)
+        );
+
+        // the 503 must not count against the endpoint's real traffic
+        assert_eq!(endpoint.success_count(), 0);
+        assert_eq!(endpoint.failure_count(), 0);
+        assert!(endpoint
+            .request_windows()
+            .iter()
+            .all(|window| window.requests == 0));
+        assert!(!text.contains("router_requests_total"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn test_mirror_drops_copies_over_the_limit() {
+        let pool = Pool {
+            name: "shadow".to_string(),
+            store: Arc::new(RwLock::new(BTreeMapEndpointStore::default())),
+            health_check: None,
+        };
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let settings = MirrorSettings {
+            pool: "shadow".to_string(),
+            percent: 100.0,
+            timeout: Duration::from_secs(5),
+        };
+        let permits = Arc::new(Semaphore::new(1));
+
+        let held = permits.clone().try_acquire_owned().unwrap();
+        assert!(spawn(&settings, &permits, pool.clone(), metrics.clone(), orders()).is_none());
+        let text = metrics.render(&[], 0).unwrap();
+        assert!(text.contains(r#"router_mirror_dropped_total{route="orders"} 1"#));
+
+        drop(held);
+        let (primary, task) = spawn(&settings, &permits, pool, metrics, orders()).unwrap();
+        assert_eq!(permits.available_permits(), 0);
+        drop(primary);
+        task.await.unwrap();
+        assert_eq!(permits.available_permits(), 1);
+    }
+
+    fn orders() -> MirroredRequest {
+        MirroredRequest {
+            route: "orders".to_string(),
+            request_id: "abc".to_string(),
+            method: reqwest::Method::POST,
+            path: "/orders".to_string(),
+            query: None,
+            headers: Default::default(),
+            body: Bytes::from_static(b"{}"),
+        }
+    }
+}
@@ -1 +1,18 @@
-pub mod hashmap_endpoint_store;
+pub mod access_log;
+pub mod autoscaler;
+pub mod balancing;
+pub mod btreemap_endpoint_store;
+pub mod discovery;
+pub mod docker;
+pub mod docker_stats_collector;
+pub mod error_pages;
+pub mod events;
+pub mod headers;
+pub mod health_check;
+pub mod metrics;
+pub mod mirror;
+pub mod pools;
+pub mod route_actions;
+pub mod route_config;
+pub mod routing;
+pub mod telemetry;
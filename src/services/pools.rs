@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use crate::{app_state::EndpointStoreType, domain::Endpoint};
+
+/// Name of the only pool when no route config is given.
+pub const DEFAULT_POOL: &str = "default";
+
+/// A named group of interchangeable endpoints, balanced independently of other pools.
+#[derive(Clone)]
+pub struct Pool {
+    pub name: String,
+    pub store: EndpointStoreType,
+    /// Active checks for this pool. Pools without them deactivate endpoints whose
+    /// failures pass a tenth of their successes, checked as requests come in.
+    pub health_check: Option<HealthCheckSettings>,
+}
+
+/// Periodic `GET` of each endpoint in a pool.
+///
+/// An endpoint is deactivated after `unhealthy_threshold` failed checks in a row, and
+/// activated again after `healthy_threshold` passing ones. Any 2xx or 3xx passes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthCheckSettings {
+    pub path: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+    pub healthy_threshold: u32,
+    pub unhealthy_threshold: u32,
+}
+
+/// Every upstream pool, one of which takes requests no route matches.
+#[derive(Clone)]
+pub struct Pools {
+    pools: Vec<Pool>,
+    default: usize,
+}
+
+impl Pools {
+    /// A single pool named [`DEFAULT_POOL`].
+    pub fn single(store: EndpointStoreType) -> Self {
+        Self {
+            pools: vec![Pool {
+                name: DEFAULT_POOL.to_string(),
+                store,
+                health_check: None,
+            }],
+            default: 0,
+        }
+    }
+
+    /// `None` if no pool is named `default`.
+    pub fn new(pools: Vec<Pool>, default: &str) -> Option<Self> {
+        let default = pools.iter().position(|pool| pool.name == default)?;
+        Some(Self { pools, default })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Pool> {
+        self.pools.iter().find(|pool| pool.name == name)
+    }
+
+    pub fn default_pool(&self) -> &Pool {
+        &self.pools[self.default]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Pool> {
+        self.pools.iter()
+    }
+
+    /// Endpoints of every pool with their pool's name; pools that fail to list are skipped.
+    pub async fn all_endpoints(&self) -> Vec<(String, Endpoint)> {
+        let mut all = Vec::new();
+        for pool in &self.pools {
+            if let Ok(endpoints) = pool.store.read().await.get_all_endpoints().await {
+                all.extend(
+                    endpoints
+                        .into_iter()
+                        .map(|endpoint| (pool.name.clone(), endpoint)),
+                );
+            }
+        }
+        all
+    }
+}
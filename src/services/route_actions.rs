@@ -0,0 +1,173 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+
+use super::routing::PathMatcher;
+
+/// What a route does instead of proxying.
+#[derive(Debug, Clone)]
+pub enum RouteAction {
+    Redirect(Redirect),
+    Respond(FixedResponse),
+}
+
+impl RouteAction {
+    /// `matcher` is the route's own, which redirects take captures or the prefix from.
+    pub fn response(&self, matcher: &PathMatcher, path: &str, query: Option<&str>) -> Response {
+        match self {
+            RouteAction::Redirect(redirect) => redirect.response(matcher, path, query),
+            RouteAction::Respond(fixed) => fixed.response(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedirectTarget {
+    /// A fixed location, in which `$1` or `$name` stand for groups of a regex route.
+    To(String),
+    /// The route's prefix swapped for this one, keeping the rest of the path.
+    ReplacePrefix(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub target: RedirectTarget,
+    /// 301, 302, 303, 307 or 308.
+    pub status: StatusCode,
+    /// Append the request's query string to the location.
+    pub keep_query: bool,
+}
+
+impl Redirect {
+    pub fn location(&self, matcher: &PathMatcher, path: &str, query: Option<&str>) -> String {
+        let mut location = match (&self.target, matcher) {
+            (RedirectTarget::To(to), PathMatcher::Regex(regex)) => match regex.captures(path) {
+                Some(captures) => {
+                    let mut expanded = String::new();
+                    captures.expand(to, &mut expanded);
+                    expanded
+                }
+                None => to.clone(),
+            },
+            (RedirectTarget::To(to), PathMatcher::Prefix(_)) => to.clone(),
+            (RedirectTarget::ReplacePrefix(new), PathMatcher::Prefix(prefix)) => {
+                let rest = path
+                    .strip_prefix(prefix.trim_end_matches('/'))
+                    .unwrap_or(path);
+                let location = format!("{}{}", new.trim_end_matches('/'), rest);
+                if location.is_empty() {
+                    "/".to_string()
+                } else {
+                    location
+                }
+            }
+            (RedirectTarget::ReplacePrefix(new), PathMatcher::Regex(_)) => new.clone(),
+        };
+        if let Some(query) = query.filter(|query| self.keep_query && !query.is_empty()) {
+            location.push(if location.contains('?') { '&' } else { '?' });
+            location.push_str(query);
+        }
+        location
+    }
+
+    fn response(&self, matcher: &PathMatcher, path: &str, query: Option<&str>) -> Response {
+        let location = self.location(matcher, path, query);
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = self.status;
+        match HeaderValue::from_str(&location) {
+            Ok(value) => {
+                response.headers_mut().insert(header::LOCATION, value);
+            }
+            Err(_) => {
+                tracing::warn!(location, "redirect location is not a valid header value");
+                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            }
+        }
+        response
+    }
+}
+
+/// A canned response, with the body loaded once when the routes are.
+#[derive(Debug, Clone)]
+pub struct FixedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl FixedResponse {
+    pub fn response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+/// Content type for a body file, from its extension.
+pub fn content_type_for(path: &str) -> &'static str {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str());
+    match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("json") => "application/json",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("txt") | None => "text/plain; charset=utf-8",
+        Some(_) => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn test_redirect_location() {
+        let redirect = |target, keep_query| Redirect {
+            target,
+            status: StatusCode::MOVED_PERMANENTLY,
+            keep_query,
+        };
+        let prefix = PathMatcher::Prefix("/old/".to_string());
+        let regex = PathMatcher::Regex(Regex::new(r"^/blog/(?P<year>\d{4})/(.+)$").unwrap());
+
+        let moved = redirect(RedirectTarget::ReplacePrefix("/new".to_string()), true);
+        assert_eq!(
+            moved.location(&prefix, "/old/a/b", Some("x=1")),
+            "/new/a/b?x=1"
+        );
+        assert_eq!(moved.location(&prefix, "/old", None), "/new");
+        let to_root = redirect(RedirectTarget::ReplacePrefix("/".to_string()), false);
+        assert_eq!(to_root.location(&prefix, "/old", Some("x=1")), "/");
+
+        let posts = redirect(
+            RedirectTarget::To("https://blog.example.com/${year}/$2?ref=old".to_string()),
+            true,
+        );
+        assert_eq!(
+            posts.location(&regex, "/blog/2023/hello", Some("utm=a")),
+            "https://blog.example.com/2023/hello?ref=old&utm=a"
+        );
+
+        let response = moved.response(&prefix, "/old/a", None);
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.headers()[header::LOCATION], "/new/a");
+
+        assert_eq!(
+            content_type_for("/srv/503.HTML"),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            content_type_for("conf.d/maintenance"),
+            "text/plain; charset=utf-8"
+        );
+    }
+}
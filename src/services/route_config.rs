@@ -0,0 +1,1098 @@
+use std::{collections::BTreeMap, fmt, path::Path, sync::Arc, time::Duration};
+
+use axum::{
+    body::Bytes,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+};
+use regex::Regex;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use super::{
+    balancing::BalancingStrategy,
+    btreemap_endpoint_store::BTreeMapEndpointStore,
+    headers::{HeaderPolicies, HeaderPolicy, Template},
+    mirror::MirrorSettings,
+    pools::{HealthCheckSettings, Pool, Pools, DEFAULT_POOL},
+    route_actions::{content_type_for, FixedResponse, Redirect, RedirectTarget, RouteAction},
+    routing::{
+        Condition, HostMatcher, PathMatcher, PathRewrite, Route, RouteTable, TrafficSplit,
+        ValueMatcher,
+    },
+};
+use crate::domain::{Endpoint, EndpointStore};
+
+/// Upstream pools and the routes between them, read from a JSON file:
+///
+/// ```json
+/// {
+///   "default_pool": "web",
+///   "pools": {
+///     "api-primary": { "endpoints": ["http://api-primary:8080"] },
+///     "api": {
+///       "endpoints": ["http://api-1:8080", "http://api-2:8080"],
+///       "strategy": "resource_aware",
+///       "health_check": { "path": "/healthz", "interval_secs": 5 }
+///     },
+///     "static": { "endpoints": ["http://cdn-origin:80"] }
+///   },
+///   "routes": [
+///     { "hosts": ["api.example.com"], "pool": "api" },
+///     { "prefix": "/api", "when": { "method": ["POST", "PUT"] }, "pool": "api-primary" },
+///     { "prefix": "/api", "rewrite": { "strip_prefix": "/api" }, "pool": "api" },
+///     { "regex": "\\.(css|js|png)$", "pool": "static" },
+///     {
+///       "name": "checkout",
+///       "prefix": "/checkout",
+///       "split": { "weights": { "api": 95, "api-primary": 5 } }
+///     },
+///     { "prefix": "/old-api", "redirect": { "replace_prefix": "/api" } },
+///     { "prefix": "/healthz", "respond": { "body": "ok" } }
+///   ],
+///   "unknown_host_status": 421,
+///   "response_headers": { "remove": ["Server"] }
+/// }
+/// ```
+///
+/// The default pool need not be listed under `pools`. Discovery sources feed the pool their
+/// settings name, or the default pool when they name none.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    #[serde(default = "default_pool_name")]
+    pub default_pool: String,
+    #[serde(default)]
+    pub pools: BTreeMap<String, PoolConfig>,
+    #[serde(default)]
+    pub routes: Vec<RouteConfigRule>,
+    /// 421 or 404 for requests whose host no route names; unset sends them on as usual.
+    pub unknown_host_status: Option<u16>,
+    /// Header changes for every proxied request, before any route's own.
+    #[serde(default)]
+    pub request_headers: HeaderPolicyConfig,
+    #[serde(default)]
+    pub response_headers: HeaderPolicyConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolConfig {
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    /// `round_robin` or `resource_aware`; `BALANCING_STRATEGY` when unset.
+    pub strategy: Option<String>,
+    pub health_check: Option<HealthCheckConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthCheckConfig {
+    #[serde(default = "default_health_path")]
+    pub path: String,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default = "default_healthy_threshold")]
+    pub healthy_threshold: u32,
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+}
+
+/// At most one of `prefix` and `regex`; a route with neither needs `hosts` or `when` and
+/// matches every path.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfigRule {
+    /// Shown to header templates as `{route}`; the pool name when unset. Split and mirror
+    /// routes, which the admin API finds by name, need one, and a name set here must not be
+    /// any other route's.
+    pub name: Option<String>,
+    /// Exact names or `*.example.com` wildcards; any host when empty.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    pub prefix: Option<String>,
+    pub regex: Option<String>,
+    pub when: Option<ConditionConfig>,
+    pub rewrite: Option<RewriteConfig>,
+    #[serde(default)]
+    pub request_headers: HeaderPolicyConfig,
+    #[serde(default)]
+    pub response_headers: HeaderPolicyConfig,
+    /// Exactly one of `pool`, `split`, `redirect` and `respond`.
+    pub pool: Option<String>,
+    pub split: Option<SplitConfig>,
+    pub mirror: Option<MirrorConfig>,
+    pub redirect: Option<RedirectConfig>,
+    pub respond: Option<RespondConfig>,
+}
+
+/// See [`Redirect`]; exactly one of `to` and `replace_prefix`, which needs a prefix route.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedirectConfig {
+    pub to: Option<String>,
+    pub replace_prefix: Option<String>,
+    #[serde(default = "default_redirect_status")]
+    pub status: u16,
+    #[serde(default = "default_keep_query")]
+    pub keep_query: bool,
+}
+
+/// See [`FixedResponse`]; at most one of `body` and `body_file`, which is read when the
+/// routes are loaded. `Content-Type` defaults to one for the file's extension.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RespondConfig {
+    #[serde(default = "default_respond_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<String>,
+    pub body_file: Option<String>,
+}
+
+/// See [`MirrorSettings`].
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorConfig {
+    pub pool: String,
+    #[serde(default = "default_mirror_percent")]
+    pub percent: f64,
+    #[serde(default = "default_mirror_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// See [`TrafficSplit`]:
+///
+/// ```json
+/// { "weights": { "stable": 95, "canary": 5 }, "header": "X-Pool", "cookie": "pool" }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SplitConfig {
+    pub weights: BTreeMap<String, u32>,
+    pub header: Option<String>,
+    pub cookie: Option<String>,
+}
+
+/// See [`HeaderPolicy`]. Values of `set` and `add` are [`Template`]s:
+///
+/// ```json
+/// {
+///   "remove": ["Server"],
+///   "rename": { "X-Old-Name": "X-New-Name" },
+///   "set": { "Strict-Transport-Security": "max-age=31536000" },
+///   "add": { "X-Forwarded-For": "{client_ip}" }
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeaderPolicyConfig {
+    #[serde(default)]
+    pub remove: Vec<String>,
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    #[serde(default)]
+    pub set: BTreeMap<String, String>,
+    #[serde(default)]
+    pub add: BTreeMap<String, String>,
+}
+
+/// See [`PathRewrite`]; `regex` and `replacement` go together.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RewriteConfig {
+    pub strip_prefix: Option<String>,
+    pub regex: Option<String>,
+    pub replacement: Option<String>,
+    pub add_prefix: Option<String>,
+}
+
+/// Exactly one of `all`, `any`, `header`, `query` and `method`:
+///
+/// ```json
+/// { "all": [
+///     { "header": "X-Tenant", "equals": "acme" },
+///     { "any": [{ "query": "beta", "regex": "^(1|true)$" }, { "method": "POST" }] }
+/// ] }
+/// ```
+///
+/// A `header` or `query` test with neither `equals` nor `regex` only needs it present.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionConfig {
+    pub all: Option<Vec<ConditionConfig>>,
+    pub any: Option<Vec<ConditionConfig>>,
+    pub header: Option<String>,
+    pub query: Option<String>,
+    pub method: Option<Methods>,
+    pub equals: Option<String>,
+    pub regex: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Methods {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn default_pool_name() -> String {
+    DEFAULT_POOL.to_string()
+}
+
+fn default_mirror_percent() -> f64 {
+    100.0
+}
+
+fn default_mirror_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_redirect_status() -> u16 {
+    301
+}
+
+fn default_keep_query() -> bool {
+    true
+}
+
+fn default_respond_status() -> u16 {
+    200
+}
+
+fn default_health_path() -> String {
+    "/".to_string()
+}
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_healthy_threshold() -> u32 {
+    2
+}
+
+fn default_unhealthy_threshold() -> u32 {
+    3
+}
+
+#[derive(Debug)]
+pub enum RouteConfigError {
+    Read(std::io::Error),
+    Parse(serde_json::Error),
+    UnknownPool(String),
+    UnknownStrategy(String),
+    InvalidEndpoint(String),
+    InvalidRegex(regex::Error),
+    InvalidRoute(String),
+    BodyFile(String, std::io::Error),
+}
+
+impl fmt::Display for RouteConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteConfigError::Read(e) => write!(f, "cannot read route config: {}", e),
+            RouteConfigError::Parse(e) => write!(f, "invalid route config: {}", e),
+            RouteConfigError::UnknownPool(pool) => write!(f, "route to unknown pool {}", pool),
+            RouteConfigError::UnknownStrategy(strategy) => {
+                write!(f, "unknown balancing strategy {}", strategy)
+            }
+            RouteConfigError::InvalidEndpoint(uri) => write!(f, "invalid endpoint URI {}", uri),
+            RouteConfigError::InvalidRegex(e) => write!(f, "invalid route regex: {}", e),
+            RouteConfigError::InvalidRoute(reason) => write!(f, "invalid route: {}", reason),
+            RouteConfigError::BodyFile(path, e) => {
+                write!(f, "cannot read response body {}: {}", path, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RouteConfigError {}
+
+impl RouteConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RouteConfigError> {
+        let content = std::fs::read_to_string(path).map_err(RouteConfigError::Read)?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, RouteConfigError> {
+        serde_json::from_str(content).map_err(RouteConfigError::Parse)
+    }
+
+    /// Create the pools, using `default_store` for the default pool so discovery can
+    /// keep feeding it.
+    pub async fn build(
+        mut self,
+        default_store: BTreeMapEndpointStore,
+    ) -> Result<(Pools, RouteTable), RouteConfigError> {
+        let default_config = self.pools.remove(&self.default_pool).unwrap_or_default();
+        let mut pools = vec![build_pool(&self.default_pool, default_config, default_store).await?];
+        for (name, config) in self.pools {
+            pools.push(build_pool(&name, config, BTreeMapEndpointStore::default()).await?);
+        }
+
+        let named: Vec<String> = self
+            .routes
+            .iter()
+            .filter_map(|rule| rule.name.clone())
+            .collect();
+        let routes = self
+            .routes
+            .into_iter()
+            .map(|rule| {
+                let known = |name: &String| pools.iter().any(|pool| pool.name == *name);
+                let has_action = rule.redirect.is_some() || rule.respond.is_some();
+                let (pool, split) = match (rule.pool, rule.split) {
+                    (None, None) if has_action => (self.default_pool.clone(), None),
+                    (Some(pool), None) if !has_action && known(&pool) => (pool, None),
+                    (Some(pool), None) if !has_action => {
+                        return Err(RouteConfigError::UnknownPool(pool))
+                    }
+                    (None, Some(split)) if !has_action => {
+                        if let Some(unknown) = split.weights.keys().find(|name| !known(name)) {
+                            return Err(RouteConfigError::UnknownPool(unknown.clone()));
+                        }
+                        let split = build_split(split)?;
+                        let heaviest = split
+                            .weights()
+                            .into_iter()
+                            .rev()
+                            .max_by_key(|(_, weight)| *weight)
+                            .map(|(pool, _)| pool)
+                            .expect("a split has weights");
+                        (heaviest, Some(Arc::new(split)))
+                    }
+                    _ => {
+                        return Err(RouteConfigError::InvalidRoute(
+                            "a route needs exactly one of pool, split, redirect and respond".to_string(),
+                        ))
+                    }
+                };
+                let hosts = rule
+                    .hosts
+                    .iter()
+                    .map(|host| {
+                        HostMatcher::parse(host).ok_or_else(|| {
+                            RouteConfigError::InvalidRoute(format!("invalid host pattern {}", host))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let when = rule.when.map(build_condition).transpose()?;
+                let matcher = match (rule.prefix, rule.regex) {
+                    (None, None) if !hosts.is_empty() || when.is_some() => {
+                        PathMatcher::Prefix("/".to_string())
+                    }
+                    (Some(prefix), None) => PathMatcher::Prefix(prefix),
+                    (None, Some(regex)) => PathMatcher::Regex(
+                        Regex::new(&regex).map_err(RouteConfigError::InvalidRegex)?,
+                    ),
+                    _ => {
+                        return Err(RouteConfigError::InvalidRoute(format!(
+                            "route to {} needs hosts, a prefix, a regex or conditions, and not both of prefix and regex",
+                            pool
+                        )))
+                    }
+                };
+                let action = match (rule.redirect, rule.respond) {
+                    (None, None) => None,
+                    (Some(redirect), None) => {
+                        Some(RouteAction::Redirect(build_redirect(&matcher, redirect)?))
+                    }
+                    (None, Some(respond)) => {
+                        Some(RouteAction::Respond(build_fixed_response(respond)?))
+                    }
+                    (Some(_), Some(_)) => {
+                        return Err(RouteConfigError::InvalidRoute(
+                            "a route needs exactly one of pool, split, redirect and respond"
+                                .to_string(),
+                        ))
+                    }
+                };
+                if action.is_some() && (rule.mirror.is_some() || rule.rewrite.is_some()) {
+                    return Err(RouteConfigError::InvalidRoute(
+                        "redirect and respond routes take no mirror or rewrite".to_string(),
+                    ));
+                }
+                let mirror = match rule.mirror {
+                    None => None,
+                    Some(mirror) if !known(&mirror.pool) => {
+                        return Err(RouteConfigError::UnknownPool(mirror.pool))
+                    }
+                    Some(mirror) if !(0.0..=100.0).contains(&mirror.percent) => {
+                        return Err(RouteConfigError::InvalidRoute(format!(
+                            "mirror percent must be between 0 and 100, got {}",
+                            mirror.percent
+                        )))
+                    }
+                    Some(mirror)
+                        if mirror.pool == pool
+                            || split.as_ref().is_some_and(|split| {
+                                split.weights().iter().any(|(name, _)| *name == mirror.pool)
+                            }) =>
+                    {
+                        return Err(RouteConfigError::InvalidRoute(format!(
+                            "route mirrors to {}, which already serves it",
+                            mirror.pool
+                        )))
+                    }
+                    Some(mirror) => Some(MirrorSettings {
+                        pool: mirror.pool,
+                        percent: mirror.percent,
+                        timeout: Duration::from_millis(mirror.timeout_ms.max(1)),
+                    }),
+                };
+                let rewrite = rule
+                    .rewrite
+                    .map(|rewrite| build_rewrite(&pool, rewrite))
+                    .transpose()?;
+                let name = match rule.name {
+                    Some(name) => name,
+                    None if split.is_some() || mirror.is_some() => {
+                        return Err(RouteConfigError::InvalidRoute(format!(
+                            "split and mirror routes need a name, as the route to {} has not",
+                            pool
+                        )))
+                    }
+                    None => match &action {
+                        Some(RouteAction::Redirect(_)) => "redirect".to_string(),
+                        Some(RouteAction::Respond(_)) => "respond".to_string(),
+                        None => pool.clone(),
+                    },
+                };
+                Ok(Route {
+                    name,
+                    hosts,
+                    matcher,
+                    when,
+                    rewrite,
+                    headers: HeaderPolicies {
+                        request: build_header_policy(rule.request_headers)?,
+                        response: build_header_policy(rule.response_headers)?,
+                    },
+                    pool,
+                    split,
+                    mirror,
+                    action,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(name) = named
+            .iter()
+            .find(|name| routes.iter().filter(|route| route.name == **name).count() > 1)
+        {
+            return Err(RouteConfigError::InvalidRoute(format!(
+                "more than one route is named {}",
+                name
+            )));
+        }
+
+        let headers = HeaderPolicies {
+            request: build_header_policy(self.request_headers)?,
+            response: build_header_policy(self.response_headers)?,
+        };
+        let table = match self.unknown_host_status {
+            None => RouteTable::new(routes, &self.default_pool),
+            Some(status @ (404 | 421)) => RouteTable::new(routes, &self.default_pool)
+                .with_unknown_host(StatusCode::from_u16(status).expect("404 and 421 are valid")),
+            Some(status) => {
+                return Err(RouteConfigError::InvalidRoute(format!(
+                    "unknown_host_status must be 404 or 421, got {}",
+                    status
+                )))
+            }
+        };
+        let pools = Pools::new(pools, &self.default_pool).expect("default pool was added first");
+        Ok((pools, table.with_headers(headers)))
+    }
+}
+
+fn build_rewrite(pool: &str, config: RewriteConfig) -> Result<PathRewrite, RouteConfigError> {
+    let replace = match (config.regex, config.replacement) {
+        (None, None) => None,
+        (Some(regex), Some(replacement)) => Some((
+            Regex::new(&regex).map_err(RouteConfigError::InvalidRegex)?,
+            replacement,
+        )),
+        _ => {
+            return Err(RouteConfigError::InvalidRoute(format!(
+                "rewrite on route to {} needs both regex and replacement",
+                pool
+            )))
+        }
+    };
+    let slashed = |prefix: String| {
+        if prefix.starts_with('/') {
+            prefix
+        } else {
+            format!("/{}", prefix)
+        }
+    };
+    Ok(PathRewrite {
+        strip_prefix: config.strip_prefix.map(slashed),
+        replace,
+        add_prefix: config.add_prefix.map(slashed),
+    })
+}
+
+fn build_redirect(
+    matcher: &PathMatcher,
+    config: RedirectConfig,
+) -> Result<Redirect, RouteConfigError> {
+    let target = match (config.to, config.replace_prefix, matcher) {
+        (Some(to), None, _) => RedirectTarget::To(to),
+        (None, Some(prefix), PathMatcher::Prefix(_)) => RedirectTarget::ReplacePrefix(prefix),
+        (None, Some(_), PathMatcher::Regex(_)) => {
+            return Err(RouteConfigError::InvalidRoute(
+                "replace_prefix needs a prefix route".to_string(),
+            ))
+        }
+        _ => {
+            return Err(RouteConfigError::InvalidRoute(
+                "a redirect needs exactly one of to and replace_prefix".to_string(),
+            ))
+        }
+    };
+    let status = match config.status {
+        301 | 302 | 303 | 307 | 308 => {
+            StatusCode::from_u16(config.status).expect("redirect statuses are valid")
+        }
+        status => {
+            return Err(RouteConfigError::InvalidRoute(format!(
+                "redirect status must be 301, 302, 303, 307 or 308, got {}",
+                status
+            )))
+        }
+    };
+    Ok(Redirect {
+        target,
+        status,
+        keep_query: config.keep_query,
+    })
+}
+
+fn build_fixed_response(config: RespondConfig) -> Result<FixedResponse, RouteConfigError> {
+    let status = StatusCode::from_u16(config.status).map_err(|_| {
+        RouteConfigError::InvalidRoute(format!("invalid response status {}", config.status))
+    })?;
+    let (body, content_type) = match (config.body, config.body_file) {
+        (None, None) => (Bytes::new(), None),
+        (Some(body), None) => (Bytes::from(body), Some(content_type_for(""))),
+        (None, Some(path)) => match std::fs::read(&path) {
+            Ok(body) => (Bytes::from(body), Some(content_type_for(&path))),
+            Err(e) => return Err(RouteConfigError::BodyFile(path, e)),
+        },
+        (Some(_), Some(_)) => {
+            return Err(RouteConfigError::InvalidRoute(
+                "a response takes body or body_file, not both".to_string(),
+            ))
+        }
+    };
+    let mut headers = HeaderMap::new();
+    for (name, value) in &config.headers {
+        let invalid =
+            || RouteConfigError::InvalidRoute(format!("invalid header {}: {}", name, value));
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?,
+            HeaderValue::from_str(value).map_err(|_| invalid())?,
+        );
+    }
+    if let Some(content_type) = content_type {
+        headers
+            .entry(header::CONTENT_TYPE)
+            .or_insert(HeaderValue::from_static(content_type));
+    }
+    Ok(FixedResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+fn build_split(config: SplitConfig) -> Result<TrafficSplit, RouteConfigError> {
+    let mut split = TrafficSplit::new(config.weights.into_iter().collect()).ok_or_else(|| {
+        RouteConfigError::InvalidRoute(
+            "a split needs a pool with weight, and weights adding up to at most 4294967295"
+                .to_string(),
+        )
+    })?;
+    if let Some(header) = config.header {
+        split = split.with_header(HeaderName::from_bytes(header.as_bytes()).map_err(|_| {
+            RouteConfigError::InvalidRoute(format!("invalid header name {}", header))
+        })?);
+    }
+    if let Some(cookie) = config.cookie {
+        split = split.with_cookie(cookie);
+    }
+    Ok(split)
+}
+
+fn build_header_policy(config: HeaderPolicyConfig) -> Result<HeaderPolicy, RouteConfigError> {
+    let name = |name: &str| {
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| RouteConfigError::InvalidRoute(format!("invalid header name {}", name)))
+    };
+    let templates = |headers: BTreeMap<String, String>| {
+        headers
+            .iter()
+            .map(|(header, template)| {
+                let template = Template::parse(template)
+                    .map_err(|e| RouteConfigError::InvalidRoute(e.to_string()))?;
+                Ok((name(header)?, template))
+            })
+            .collect::<Result<Vec<_>, RouteConfigError>>()
+    };
+    Ok(HeaderPolicy {
+        remove: config
+            .remove
+            .iter()
+            .map(|header| name(header))
+            .collect::<Result<_, _>>()?,
+        rename: config
+            .rename
+            .iter()
+            .map(|(from, to)| Ok((name(from)?, name(to)?)))
+            .collect::<Result<_, RouteConfigError>>()?,
+        set: templates(config.set)?,
+        add: templates(config.add)?,
+    })
+}
+
+fn build_condition(config: ConditionConfig) -> Result<Condition, RouteConfigError> {
+    let invalid = |reason: String| RouteConfigError::InvalidRoute(reason);
+    let value = match (config.equals, config.regex) {
+        (None, None) => ValueMatcher::Present,
+        (Some(value), None) => ValueMatcher::Equals(value),
+        (None, Some(regex)) => {
+            ValueMatcher::Regex(Regex::new(&regex).map_err(RouteConfigError::InvalidRegex)?)
+        }
+        (Some(_), Some(_)) => {
+            return Err(invalid(
+                "a condition takes equals or regex, not both".to_string(),
+            ))
+        }
+    };
+    let takes_value = !matches!(value, ValueMatcher::Present);
+    let nested = |conditions: Vec<ConditionConfig>| -> Result<Vec<Condition>, RouteConfigError> {
+        if conditions.is_empty() {
+            return Err(invalid(
+                "all and any need at least one condition".to_string(),
+            ));
+        }
+        conditions.into_iter().map(build_condition).collect()
+    };
+
+    match (
+        config.all,
+        config.any,
+        config.header,
+        config.query,
+        config.method,
+    ) {
+        (Some(all), None, None, None, None) if !takes_value => Ok(Condition::All(nested(all)?)),
+        (None, Some(any), None, None, None) if !takes_value => Ok(Condition::Any(nested(any)?)),
+        (None, None, Some(name), None, None) => HeaderName::from_bytes(name.as_bytes())
+            .map(|header| Condition::Header(header, value))
+            .map_err(|_| invalid(format!("invalid header name {}", name))),
+        (None, None, None, Some(name), None) => Ok(Condition::Query(name, value)),
+        (None, None, None, None, Some(methods)) if !takes_value => {
+            let methods = match methods {
+                Methods::One(method) => vec![method],
+                Methods::Many(methods) => methods,
+            };
+            if methods.is_empty() {
+                return Err(invalid("method needs at least one method".to_string()));
+            }
+            methods
+                .iter()
+                .map(|method| {
+                    Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                        .map_err(|_| invalid(format!("invalid method {}", method)))
+                })
+                .collect::<Result<_, _>>()
+                .map(Condition::Method)
+        }
+        _ => Err(invalid(
+            "a condition needs exactly one of all, any, header, query and method, \
+             and takes equals or regex only with header or query"
+                .to_string(),
+        )),
+    }
+}
+
+async fn build_pool(
+    name: &str,
+    config: PoolConfig,
+    store: BTreeMapEndpointStore,
+) -> Result<Pool, RouteConfigError> {
+    let mut store = match config.strategy {
+        Some(strategy) => store.with_strategy(
+            BalancingStrategy::parse(&strategy)
+                .ok_or(RouteConfigError::UnknownStrategy(strategy))?,
+        ),
+        None => store,
+    };
+    for uri in config.endpoints {
+        let parsed = Endpoint::parse_uri(&uri)
+            .ok_or_else(|| RouteConfigError::InvalidEndpoint(uri.clone()))?;
+        // the same endpoint listed twice is harmless
+        let _ = store.add_endpoint(Endpoint::new(parsed)).await;
+    }
+
+    Ok(Pool {
+        name: name.to_string(),
+        store: Arc::new(RwLock::new(store)),
+        health_check: config.health_check.map(|check| HealthCheckSettings {
+            path: if check.path.starts_with('/') {
+                check.path
+            } else {
+                format!("/{}", check.path)
+            },
+            interval: Duration::from_secs(check.interval_secs.max(1)),
+            timeout: Duration::from_millis(check.timeout_ms),
+            healthy_threshold: check.healthy_threshold.max(1),
+            unhealthy_threshold: check.unhealthy_threshold.max(1),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+    use crate::services::routing::RouteRequest;
+
+    #[tokio::test]
+    async fn test_build() {
+        let config = RouteConfig::parse(
+            r#"{
+                "default_pool": "web",
+                "pools": {
+                    "api": {
+                        "endpoints": ["http://api-1:8080", "http://api-2:8080"],
+                        "health_check": { "path": "healthz", "interval_secs": 5 }
+                    },
+                    "static": { "endpoints": ["http://cdn:80"], "strategy": "round_robin" }
+                },
+                "routes": [
+                    {
+                        "name": "api-v2",
+                        "prefix": "/api",
+                        "rewrite": { "strip_prefix": "api", "add_prefix": "/v2" },
+                        "request_headers": { "set": { "X-Forwarded-For": "{client_ip}" } },
+                        "pool": "api",
+                        "mirror": { "pool": "web", "percent": 12.5 }
+                    },
+                    { "regex": "\\.css$", "pool": "static" },
+                    {
+                        "name": "checkout",
+                        "prefix": "/checkout",
+                        "split": {
+                            "weights": { "api": 1, "static": 3, "web": 0 },
+                            "cookie": "pool"
+                        }
+                    },
+                    { "hosts": ["*.cdn.example.com"], "pool": "static" },
+                    {
+                        "hosts": ["*.cdn.example.com"],
+                        "when": { "any": [
+                            { "header": "X-Tenant", "equals": "acme" },
+                            { "all": [{ "query": "beta" }, { "method": ["get", "HEAD"] }] }
+                        ] },
+                        "pool": "api"
+                    }
+                ],
+                "unknown_host_status": 404,
+                "response_headers": {
+                    "remove": ["Server"],
+                    "rename": { "X-Backend": "X-Served-By" },
+                    "add": { "X-Route": "{route}" }
+                }
+            }"#,
+        )
+        .unwrap();
+        let (pools, table) = config
+            .build(BTreeMapEndpointStore::default())
+            .await
+            .unwrap();
+
+        assert_eq!(pools.default_pool().name, "web");
+        assert_eq!(pools.iter().count(), 3);
+        let api = pools.get("api").unwrap();
+        assert_eq!(
+            api.store
+                .read()
+                .await
+                .get_all_endpoints()
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let check = api.health_check.as_ref().unwrap();
+        assert_eq!(check.path, "/healthz");
+        assert_eq!(check.interval, Duration::from_secs(5));
+        assert_eq!(check.unhealthy_threshold, 3);
+        assert!(pools.get("static").unwrap().health_check.is_none());
+
+        let checkout = table.route("checkout").unwrap();
+        assert_eq!(checkout.pool, "static");
+        let split = checkout.split.as_ref().unwrap();
+        assert_eq!(split.weights().len(), 3);
+        assert_eq!(split.cookie.as_deref(), Some("pool"));
+
+        let route = &table.routes()[0];
+        assert_eq!(route.name, "api-v2");
+        let mirror = route.mirror.as_ref().unwrap();
+        assert_eq!((mirror.pool.as_str(), mirror.percent), ("web", 12.5));
+        assert_eq!(mirror.timeout, Duration::from_secs(5));
+        assert_eq!(table.routes()[1].name, "static");
+        assert!(table.routes()[1].split.is_none());
+        assert_eq!(
+            route.rewrite.as_ref().unwrap().apply("/api/users"),
+            "/v2/users"
+        );
+        assert_eq!(route.headers.request.set[0].0, "x-forwarded-for");
+        let response = &table.headers().response;
+        assert_eq!(response.remove, [axum::http::header::SERVER]);
+        assert_eq!(response.rename[0].1, "x-served-by");
+
+        let pool = |request: axum::http::request::Builder| {
+            let request = request.body(()).unwrap();
+            table
+                .pool_for(&RouteRequest::new(&request))
+                .map(str::to_string)
+        };
+        let get = |host, path| Request::get(path).header("host", host);
+        assert_eq!(
+            pool(get("img.cdn.example.com", "/api/users")),
+            Ok("static".into())
+        );
+        assert_eq!(
+            pool(get("img.cdn.example.com", "/site.css")),
+            Ok("static".into())
+        );
+        assert_eq!(pool(get("example.com", "/")), Err(StatusCode::NOT_FOUND));
+        assert_eq!(
+            pool(get("img.cdn.example.com", "/").header("x-tenant", "acme")),
+            Ok("api".into())
+        );
+        assert_eq!(pool(get("img.cdn.example.com", "/?beta")), Ok("api".into()));
+        assert_eq!(
+            pool(Request::post("/?beta").header("host", "img.cdn.example.com")),
+            Ok("static".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_actions() {
+        let page = std::env::temp_dir().join(format!("maintenance-{}.html", std::process::id()));
+        std::fs::write(&page, "<h1>Back soon</h1>").unwrap();
+        let config = RouteConfig::parse(&format!(
+            r#"{{
+                "routes": [
+                    {{ "prefix": "/healthz", "respond": {{ "body": "ok" }} }},
+                    {{ "prefix": "/old", "redirect": {{ "replace_prefix": "/new" }} }},
+                    {{
+                        "name": "maintenance",
+                        "prefix": "/shop",
+                        "respond": {{
+                            "status": 503,
+                            "headers": {{ "Retry-After": "600" }},
+                            "body_file": {:?}
+                        }}
+                    }}
+                ]
+            }}"#,
+            page.display().to_string()
+        ))
+        .unwrap();
+        let (_, table) = config
+            .build(BTreeMapEndpointStore::default())
+            .await
+            .unwrap();
+        std::fs::remove_file(&page).unwrap();
+
+        let healthz = &table.routes()[0];
+        assert_eq!(healthz.name, "respond");
+        assert_eq!(healthz.pool, DEFAULT_POOL);
+        match healthz.action.as_ref().unwrap() {
+            RouteAction::Respond(fixed) => {
+                assert_eq!(fixed.status, StatusCode::OK);
+                assert_eq!(fixed.body, "ok");
+                assert_eq!(
+                    fixed.headers[header::CONTENT_TYPE],
+                    "text/plain; charset=utf-8"
+                );
+            }
+            action => panic!("unexpected action {:?}", action),
+        }
+        match table.routes()[1].action.as_ref().unwrap() {
+            RouteAction::Redirect(redirect) => {
+                assert_eq!(redirect.status, StatusCode::MOVED_PERMANENTLY);
+                assert_eq!(
+                    redirect.target,
+                    RedirectTarget::ReplacePrefix("/new".to_string())
+                );
+                assert!(redirect.keep_query);
+            }
+            action => panic!("unexpected action {:?}", action),
+        }
+        let response = match table.route("maintenance").unwrap().action.as_ref().unwrap() {
+            RouteAction::Respond(fixed) => fixed.response(),
+            action => panic!("unexpected action {:?}", action),
+        };
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "600");
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "<h1>Back soon</h1>");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_configs() {
+        for (content, expected) in [
+            (
+                r#"{"routes": [{"prefix": "/api", "pool": "api"}]}"#,
+                "unknown pool",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/a", "regex": "b", "pool": "default"}]}"#,
+                "not both",
+            ),
+            (r#"{"routes": [{"pool": "default"}]}"#, "needs hosts"),
+            (
+                r#"{"routes": [{"hosts": ["a.*.com"], "pool": "default"}]}"#,
+                "host pattern",
+            ),
+            (r#"{"unknown_host_status": 500}"#, "404 or 421"),
+            (
+                r#"{"routes": [{"prefix": "/", "pool": "default", "mirror": {"pool": "default"}}]}"#,
+                "already serves",
+            ),
+            (
+                r#"{"pools": {"b": {}}, "routes": [{"prefix": "/", "pool": "default", "mirror": {"pool": "b", "percent": 120}}]}"#,
+                "between 0 and 100",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/", "split": {"weights": {"canary": 1}}}]}"#,
+                "unknown pool canary",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/", "split": {"weights": {"default": 0}}}]}"#,
+                "pool with weight",
+            ),
+            (
+                r#"{"pools": {"b": {}}, "routes": [{"prefix": "/", "split": {"weights": {"default": 4294967295, "b": 1}}}]}"#,
+                "at most 4294967295",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/", "pool": "default", "split": {"weights": {}}}]}"#,
+                "exactly one of pool, split, redirect and respond",
+            ),
+            (
+                r#"{"pools": {"b": {}}, "routes": [{"prefix": "/", "split": {"weights": {"default": 1, "b": 1}}}]}"#,
+                "need a name",
+            ),
+            (
+                r#"{"pools": {"b": {}}, "routes": [{"prefix": "/", "pool": "default", "mirror": {"pool": "b"}}]}"#,
+                "need a name",
+            ),
+            (
+                r#"{"pools": {"api": {}, "b": {}}, "routes": [
+                    {"prefix": "/api", "pool": "api"},
+                    {"name": "api", "prefix": "/b", "split": {"weights": {"api": 3, "b": 1}}}
+                ]}"#,
+                "more than one route is named api",
+            ),
+            (
+                r#"{"routes": [
+                    {"name": "a", "prefix": "/a", "pool": "default"},
+                    {"name": "a", "prefix": "/b", "pool": "default"}
+                ]}"#,
+                "more than one route is named a",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/", "rewrite": {"regex": "a"}, "pool": "default"}]}"#,
+                "regex and replacement",
+            ),
+            (
+                r#"{"routes": [{"when": {"header": "a", "query": "b"}, "pool": "default"}]}"#,
+                "exactly one",
+            ),
+            (
+                r#"{"routes": [{"when": {"method": "GET", "equals": "x"}, "pool": "default"}]}"#,
+                "exactly one",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/", "pool": "default", "respond": {}}]}"#,
+                "exactly one of pool",
+            ),
+            (
+                r#"{"routes": [{"regex": "^/a", "redirect": {"replace_prefix": "/b"}}]}"#,
+                "needs a prefix route",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/a", "redirect": {"to": "/b", "status": 200}}]}"#,
+                "301, 302, 303, 307 or 308",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/a", "respond": {"body": "", "body_file": "x"}}]}"#,
+                "not both",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/a", "respond": {"body_file": "/nonexistent/503.html"}}]}"#,
+                "cannot read response body /nonexistent/503.html",
+            ),
+            (
+                r#"{"routes": [{"prefix": "/a", "respond": {}, "mirror": {"pool": "default"}}]}"#,
+                "no mirror or rewrite",
+            ),
+            (
+                r#"{"routes": [{"when": {"any": []}, "pool": "default"}]}"#,
+                "at least one",
+            ),
+            (
+                r#"{"routes": [{"when": {"header": "a b"}, "pool": "default"}]}"#,
+                "header name",
+            ),
+            (
+                r#"{"request_headers": {"set": {"X-A": "{client}"}}}"#,
+                "unknown variable",
+            ),
+            (
+                r#"{"response_headers": {"remove": ["bad name"]}}"#,
+                "header name",
+            ),
+            (
+                r#"{"routes": [{"regex": "(", "pool": "default"}]}"#,
+                "regex",
+            ),
+            (r#"{"pools": {"api": {"strategy": "fastest"}}}"#, "strategy"),
+            (
+                r#"{"pools": {"api": {"endpoints": ["api:80"]}}}"#,
+                "endpoint",
+            ),
+        ] {
+            let error = RouteConfig::parse(content)
+                .unwrap()
+                .build(BTreeMapEndpointStore::default())
+                .await
+                .err()
+                .unwrap();
+            assert!(error.to_string().contains(expected), "{}", error);
+        }
+        assert!(RouteConfig::parse(r#"{"pool": {}}"#).is_err());
+    }
+}
@@ -0,0 +1,827 @@
+use std::sync::{Arc, RwLock};
+
+use axum::http::{header, HeaderMap, HeaderName, Method, Request, StatusCode, Uri};
+use axum_extra::extract::CookieJar;
+use rand::Rng;
+use regex::Regex;
+
+use super::{headers::HeaderPolicies, mirror::MirrorSettings, route_actions::RouteAction};
+
+/// How a route matches the request path.
+#[derive(Debug, Clone)]
+pub enum PathMatcher {
+    /// Matches whole path segments: `/api` matches `/api` and `/api/users`, not `/apiary`.
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl PathMatcher {
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            PathMatcher::Prefix(prefix) => {
+                let prefix = prefix.trim_end_matches('/');
+                prefix.is_empty()
+                    || path == prefix
+                    || path
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            }
+            PathMatcher::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+/// How a route matches the request's host name, compared case-insensitively.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostMatcher {
+    Exact(String),
+    /// `*.example.com`, kept as `.example.com`: any subdomain, but not `example.com` itself.
+    Wildcard(String),
+}
+
+impl HostMatcher {
+    /// `None` if `*` appears anywhere but as the whole first label.
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+        let matcher = match pattern.strip_prefix('*') {
+            Some(suffix) if suffix.starts_with('.') && suffix.len() > 1 => {
+                HostMatcher::Wildcard(suffix.to_string())
+            }
+            Some(_) => return None,
+            None => HostMatcher::Exact(pattern),
+        };
+        match &matcher {
+            HostMatcher::Exact(host) | HostMatcher::Wildcard(host)
+                if host.is_empty() || host.contains('*') =>
+            {
+                None
+            }
+            _ => Some(matcher),
+        }
+    }
+
+    /// `host` must already be normalized, see [`normalize_host`].
+    pub fn matches(&self, host: &str) -> bool {
+        match self {
+            HostMatcher::Exact(exact) => host == exact,
+            HostMatcher::Wildcard(suffix) => host.len() > suffix.len() && host.ends_with(suffix),
+        }
+    }
+}
+
+/// Changes a route makes to the path before it goes upstream, applied in field order.
+/// The query string is left alone.
+#[derive(Debug, Clone, Default)]
+pub struct PathRewrite {
+    /// Removed if the path starts with it, on a segment boundary.
+    pub strip_prefix: Option<String>,
+    /// The first match is replaced; the replacement may use `$1` or `$name` groups.
+    pub replace: Option<(Regex, String)>,
+    pub add_prefix: Option<String>,
+}
+
+impl PathRewrite {
+    pub fn apply(&self, path: &str) -> String {
+        let mut path = path.to_string();
+        if let Some(prefix) = &self.strip_prefix {
+            let prefix = prefix.trim_end_matches('/');
+            if PathMatcher::Prefix(prefix.to_string()).matches(&path) {
+                path = path[prefix.len()..].to_string();
+            }
+        }
+        if let Some((regex, replacement)) = &self.replace {
+            path = regex.replace(&path, replacement.as_str()).into_owned();
+        }
+        if let Some(prefix) = &self.add_prefix {
+            path = format!(
+                "{}{}",
+                prefix.trim_end_matches('/'),
+                with_leading_slash(&path)
+            );
+        }
+        with_leading_slash(&path).into_owned()
+    }
+}
+
+fn with_leading_slash(path: &str) -> std::borrow::Cow<'_, str> {
+    if path.starts_with('/') {
+        path.into()
+    } else {
+        format!("/{}", path).into()
+    }
+}
+
+/// `path` and `query` appended to the endpoint's own path, so an endpoint at
+/// `http://backend/base/` gets `/users` as `http://backend/base/users`.
+pub fn upstream_url(endpoint: &Uri, path: &str, query: Option<&str>) -> String {
+    let mut url = format!(
+        "{}://{}{}{}",
+        endpoint.scheme_str().unwrap_or("http"),
+        endpoint
+            .authority()
+            .map(|authority| authority.as_str())
+            .unwrap_or_default(),
+        endpoint.path().trim_end_matches('/'),
+        with_leading_slash(path)
+    );
+    if let Some(query) = query {
+        url.push('?');
+        url.push_str(query);
+    }
+    url
+}
+
+/// Lowercase host name without port or trailing dot.
+pub fn normalize_host(host: &str) -> String {
+    let host = match host.rsplit_once(':') {
+        // leave IPv6 literals such as `[::1]` alone
+        Some((name, port)) if !name.ends_with(':') && port.bytes().all(|b| b.is_ascii_digit()) => {
+            name
+        }
+        _ => host,
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// How a header or query parameter value must look.
+#[derive(Debug, Clone)]
+pub enum ValueMatcher {
+    Present,
+    Equals(String),
+    Regex(Regex),
+}
+
+impl ValueMatcher {
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            ValueMatcher::Present => true,
+            ValueMatcher::Equals(expected) => value == expected,
+            ValueMatcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// A test on request attributes other than host and path.
+#[derive(Debug, Clone)]
+pub enum Condition {
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    /// Any value of the header matches.
+    Header(HeaderName, ValueMatcher),
+    /// Any value of the query parameter matches, after percent-decoding.
+    Query(String, ValueMatcher),
+    Method(Vec<Method>),
+}
+
+impl Condition {
+    pub fn matches(&self, request: &RouteRequest) -> bool {
+        match self {
+            Condition::All(conditions) => conditions.iter().all(|c| c.matches(request)),
+            Condition::Any(conditions) => conditions.iter().any(|c| c.matches(request)),
+            Condition::Header(name, matcher) => request
+                .headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .any(|value| matcher.matches(value)),
+            Condition::Query(name, matcher) => request
+                .query
+                .iter()
+                .filter(|(key, _)| key == name)
+                .any(|(_, value)| matcher.matches(value)),
+            Condition::Method(methods) => methods.contains(request.method),
+        }
+    }
+}
+
+/// The parts of a request that routes look at.
+pub struct RouteRequest<'a> {
+    /// Normalized, from the URI or else the `Host` header.
+    pub host: Option<String>,
+    pub method: &'a Method,
+    pub path: &'a str,
+    pub headers: &'a HeaderMap,
+    pub query: Vec<(String, String)>,
+}
+
+impl<'a> RouteRequest<'a> {
+    pub fn new<B>(request: &'a Request<B>) -> Self {
+        let host = request
+            .uri()
+            .host()
+            .or_else(|| {
+                request
+                    .headers()
+                    .get(header::HOST)
+                    .and_then(|value| value.to_str().ok())
+            })
+            .map(normalize_host);
+        let query = request
+            .uri()
+            .query()
+            .map(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .into_owned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            host,
+            method: request.method(),
+            path: request.uri().path(),
+            headers: request.headers(),
+            query,
+        }
+    }
+}
+
+/// Weighted choice between pools for one route, adjustable while the router runs.
+///
+/// A request can pick its pool by naming it in `header` or `cookie`. Only pools in the
+/// split can be picked this way, including ones with no weight.
+#[derive(Debug)]
+pub struct TrafficSplit {
+    weights: RwLock<Vec<(String, u32)>>,
+    pub header: Option<HeaderName>,
+    pub cookie: Option<String>,
+}
+
+/// Some pool has weight, and the total fits the `u32` that [`TrafficSplit::choose`] rolls in.
+fn valid_weights(weights: &[(String, u32)]) -> bool {
+    weights
+        .iter()
+        .try_fold(0u32, |total, (_, weight)| total.checked_add(*weight))
+        .is_some_and(|total| total > 0)
+}
+
+impl TrafficSplit {
+    /// `None` unless some pool has weight and the weights add up to at most `u32::MAX`.
+    pub fn new(weights: Vec<(String, u32)>) -> Option<Self> {
+        if !valid_weights(&weights) {
+            return None;
+        }
+        Some(Self {
+            weights: RwLock::new(weights),
+            header: None,
+            cookie: None,
+        })
+    }
+
+    pub fn with_header(self, header: HeaderName) -> Self {
+        Self {
+            header: Some(header),
+            ..self
+        }
+    }
+
+    pub fn with_cookie(self, cookie: impl Into<String>) -> Self {
+        Self {
+            cookie: Some(cookie.into()),
+            ..self
+        }
+    }
+
+    pub fn weights(&self) -> Vec<(String, u32)> {
+        self.weights.read().expect("split lock poisoned").clone()
+    }
+
+    /// Replace the weights; refused, returning `false`, on weights [`TrafficSplit::new`] refuses.
+    pub fn set_weights(&self, weights: Vec<(String, u32)>) -> bool {
+        if !valid_weights(&weights) {
+            return false;
+        }
+        *self.weights.write().expect("split lock poisoned") = weights;
+        true
+    }
+
+    /// The pool named by the request, or else a weighted random one.
+    pub fn choose(&self, request: &RouteRequest, rng: &mut impl Rng) -> String {
+        let weights = self.weights.read().expect("split lock poisoned");
+        if let Some(forced) = self.forced(request) {
+            if let Some((pool, _)) = weights.iter().find(|(pool, _)| *pool == forced) {
+                return pool.clone();
+            }
+        }
+
+        let total: u32 = weights.iter().map(|(_, weight)| weight).sum();
+        let mut roll = rng.gen_range(0..total);
+        for (pool, weight) in weights.iter() {
+            if roll < *weight {
+                return pool.clone();
+            }
+            roll -= weight;
+        }
+        unreachable!("roll is below the total weight")
+    }
+
+    fn forced(&self, request: &RouteRequest) -> Option<String> {
+        let header = self.header.as_ref().and_then(|name| {
+            request
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+        });
+        header.or_else(|| {
+            let cookie = self.cookie.as_ref()?;
+            CookieJar::from_headers(request.headers)
+                .get(cookie)
+                .map(|cookie| cookie.value().to_string())
+        })
+    }
+}
+
+/// Sends requests whose host and path match, and that pass `when` if set, to `pool`.
+/// An empty `hosts` matches any host.
+#[derive(Debug, Clone)]
+pub struct Route {
+    /// Shown to header templates as `{route}`.
+    pub name: String,
+    pub hosts: Vec<HostMatcher>,
+    pub matcher: PathMatcher,
+    pub when: Option<Condition>,
+    pub rewrite: Option<PathRewrite>,
+    /// Applied after the table's own policies.
+    pub headers: HeaderPolicies,
+    /// With a split, its heaviest pool when the route was loaded; with an action, the
+    /// default pool, which is never used.
+    pub pool: String,
+    pub split: Option<Arc<TrafficSplit>>,
+    pub mirror: Option<MirrorSettings>,
+    /// Answered by the router itself instead of a pool.
+    pub action: Option<RouteAction>,
+}
+
+impl Route {
+    /// `pool`, or the split's choice for this request.
+    pub fn choose_pool(&self, request: &RouteRequest) -> String {
+        match &self.split {
+            Some(split) => split.choose(request, &mut rand::thread_rng()),
+            None => self.pool.clone(),
+        }
+    }
+
+    fn host_rank(&self, host: Option<&str>) -> Option<HostRank> {
+        if self.hosts.is_empty() {
+            return Some(HostRank::Any);
+        }
+        let host = host?;
+        self.hosts
+            .iter()
+            .filter(|matcher| matcher.matches(host))
+            .map(|matcher| match matcher {
+                HostMatcher::Exact(_) => HostRank::Exact,
+                HostMatcher::Wildcard(_) => HostRank::Wildcard,
+            })
+            .min()
+    }
+}
+
+/// How specifically a route matched the host; lower ranks are tried first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum HostRank {
+    Exact,
+    Wildcard,
+    Any,
+}
+
+/// Picks the upstream pool for a request.
+///
+/// Routes naming the request's host exactly are tried first, then wildcard host routes,
+/// then routes for any host. Within each group, routes with conditions are tried first in
+/// the order given and the first whose path and conditions match wins. Then regex routes
+/// are tried in order, and otherwise the longest matching prefix wins. Requests nothing
+/// matches go to the default pool, unless `unknown_host` is set and no route names their
+/// host, in which case they are answered with that status.
+#[derive(Debug, Clone)]
+pub struct RouteTable {
+    routes: Vec<Route>,
+    default_pool: String,
+    unknown_host: Option<StatusCode>,
+    headers: HeaderPolicies,
+}
+
+impl RouteTable {
+    pub fn new(routes: Vec<Route>, default_pool: impl Into<String>) -> Self {
+        Self {
+            routes,
+            default_pool: default_pool.into(),
+            unknown_host: None,
+            headers: HeaderPolicies::default(),
+        }
+    }
+
+    /// Header changes for every proxied request, before any route's own.
+    pub fn with_headers(self, headers: HeaderPolicies) -> Self {
+        Self { headers, ..self }
+    }
+
+    pub fn headers(&self) -> &HeaderPolicies {
+        &self.headers
+    }
+
+    /// Reject requests for hosts no route names, typically with 421 or 404.
+    pub fn with_unknown_host(self, status: StatusCode) -> Self {
+        Self {
+            unknown_host: Some(status),
+            ..self
+        }
+    }
+
+    pub fn default_pool(&self) -> &str {
+        &self.default_pool
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// The first route with this name.
+    pub fn route(&self, name: &str) -> Option<&Route> {
+        self.routes.iter().find(|route| route.name == name)
+    }
+
+    /// The route for a request, `None` for the default pool, or the status to answer an
+    /// unknown host with.
+    pub fn resolve(&self, request: &RouteRequest) -> Result<Option<&Route>, StatusCode> {
+        let host = request.host.as_deref();
+        for rank in [HostRank::Exact, HostRank::Wildcard, HostRank::Any] {
+            let candidates: Vec<&Route> = self
+                .routes
+                .iter()
+                .filter(|route| route.host_rank(host) == Some(rank))
+                .collect();
+            if rank == HostRank::Any {
+                if let Some(status) = self.unknown_host {
+                    let known = self
+                        .routes
+                        .iter()
+                        .any(|route| !route.hosts.is_empty() && route.host_rank(host).is_some());
+                    if !known {
+                        return Err(status);
+                    }
+                }
+            }
+            if let Some(route) = best_match(&candidates, request) {
+                return Ok(Some(route));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Pool for a request, or the status to answer an unknown host with. Routes with a
+    /// split give their heaviest pool; see [`Route::choose_pool`].
+    pub fn pool_for(&self, request: &RouteRequest) -> Result<&str, StatusCode> {
+        Ok(self
+            .resolve(request)?
+            .map(|route| route.pool.as_str())
+            .unwrap_or(&self.default_pool))
+    }
+}
+
+/// First matching route with conditions, else the first matching regex route, or else the
+/// longest matching prefix.
+fn best_match<'a>(routes: &[&'a Route], request: &RouteRequest) -> Option<&'a Route> {
+    let path = request.path;
+    let conditional = routes.iter().find(|route| {
+        route
+            .when
+            .as_ref()
+            .is_some_and(|when| route.matcher.matches(path) && when.matches(request))
+    });
+    let unconditional = || routes.iter().filter(|route| route.when.is_none());
+    let regex_match = || {
+        unconditional().find(|route| {
+            matches!(route.matcher, PathMatcher::Regex(_)) && route.matcher.matches(path)
+        })
+    };
+    let longest_prefix = || {
+        unconditional()
+            .filter_map(|route| match &route.matcher {
+                PathMatcher::Prefix(prefix) if route.matcher.matches(path) => {
+                    Some((prefix.trim_end_matches('/').len(), route))
+                }
+                _ => None,
+            })
+            // the first of equally long prefixes wins
+            .rev()
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, route)| route)
+    };
+
+    conditional
+        .or_else(regex_match)
+        .or_else(longest_prefix)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn route(hosts: &[&str], prefix: &str, pool: &str) -> Route {
+        Route {
+            name: pool.to_string(),
+            hosts: hosts
+                .iter()
+                .map(|host| HostMatcher::parse(host).unwrap())
+                .collect(),
+            matcher: PathMatcher::Prefix(prefix.to_string()),
+            when: None,
+            rewrite: None,
+            headers: HeaderPolicies::default(),
+            pool: pool.to_string(),
+            split: None,
+            mirror: None,
+            action: None,
+        }
+    }
+
+    fn request(host: Option<&str>, method: Method, uri: &str) -> Request<()> {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(host) = host {
+            builder = builder.header(header::HOST, host);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let table = RouteTable::new(
+            vec![
+                route(&[], "/api", "api"),
+                route(&[], "/api/admin/", "admin"),
+                Route {
+                    name: "static".to_string(),
+                    hosts: Vec::new(),
+                    matcher: PathMatcher::Regex(Regex::new(r"\.(css|js)$").unwrap()),
+                    when: None,
+                    rewrite: None,
+                    headers: HeaderPolicies::default(),
+                    pool: "static".to_string(),
+                    split: None,
+                    mirror: None,
+                    action: None,
+                },
+            ],
+            "web",
+        );
+        let pool = |path| {
+            table
+                .pool_for(&RouteRequest::new(&request(None, Method::GET, path)))
+                .unwrap()
+        };
+
+        assert_eq!(pool("/api"), "api");
+        assert_eq!(pool("/api/users"), "api");
+        assert_eq!(pool("/api/admin"), "admin");
+        assert_eq!(pool("/api/admin/users"), "admin");
+        assert_eq!(pool("/api/admin/app.js"), "static");
+        assert_eq!(pool("/apiary"), "web");
+        assert_eq!(pool("/"), "web");
+    }
+
+    #[test]
+    fn test_virtual_hosts() {
+        let routes = vec![
+            route(&["*.example.com"], "/", "tenants"),
+            route(&["api.example.com"], "/", "api"),
+            route(&["api.example.com"], "/v2", "api-v2"),
+            route(&["shop.example.org", "www.shop.example.org"], "/", "shop"),
+            route(&[], "/static", "static"),
+        ];
+        let table = RouteTable::new(routes.clone(), "web");
+        let pool = |host: &str, path| {
+            table
+                .pool_for(&RouteRequest::new(&request(Some(host), Method::GET, path)))
+                .map(str::to_string)
+        };
+
+        assert_eq!(pool("API.example.com:8443", "/users"), Ok("api".into()));
+        assert_eq!(pool("api.example.com", "/v2/users"), Ok("api-v2".into()));
+        assert_eq!(pool("acme.example.com", "/"), Ok("tenants".into()));
+        assert_eq!(pool("a.b.example.com.", "/"), Ok("tenants".into()));
+        assert_eq!(pool("example.com", "/"), Ok("web".into()));
+        assert_eq!(pool("www.shop.example.org", "/cart"), Ok("shop".into()));
+        assert_eq!(pool("other.net", "/static/app.js"), Ok("static".into()));
+        assert_eq!(
+            table.pool_for(&RouteRequest::new(&request(None, Method::GET, "/"))),
+            Ok("web")
+        );
+
+        let strict =
+            RouteTable::new(routes, "web").with_unknown_host(StatusCode::MISDIRECTED_REQUEST);
+        let strict_pool =
+            |host, path| strict.pool_for(&RouteRequest::new(&request(host, Method::GET, path)));
+        assert_eq!(
+            strict_pool(Some("other.net"), "/static/app.js"),
+            Err(StatusCode::MISDIRECTED_REQUEST)
+        );
+        assert_eq!(strict_pool(None, "/"), Err(StatusCode::MISDIRECTED_REQUEST));
+        assert_eq!(
+            strict_pool(Some("acme.example.com"), "/static/x"),
+            Ok("tenants")
+        );
+        assert_eq!(
+            strict_pool(None, "http://acme.example.com/static/x"),
+            Ok("tenants")
+        );
+
+        assert_eq!(HostMatcher::parse("api.*.com"), None);
+        assert_eq!(HostMatcher::parse("*"), None);
+        assert_eq!(normalize_host("[::1]:8080"), "[::1]");
+    }
+
+    #[test]
+    fn test_conditions() {
+        let conditional = |prefix: &str, when, pool: &str| Route {
+            when: Some(when),
+            ..route(&[], prefix, pool)
+        };
+        let tenant = Condition::Header(
+            HeaderName::from_static("x-tenant"),
+            ValueMatcher::Equals("acme".to_string()),
+        );
+        let table = RouteTable::new(
+            vec![
+                route(&[], "/api/admin", "admin"),
+                conditional("/", tenant, "acme"),
+                conditional(
+                    "/api",
+                    Condition::Method(vec![Method::POST, Method::PUT]),
+                    "writes",
+                ),
+                conditional(
+                    "/api",
+                    Condition::Any(vec![
+                        Condition::Query(
+                            "beta".to_string(),
+                            ValueMatcher::Regex(Regex::new("^(1|true)$").unwrap()),
+                        ),
+                        Condition::All(vec![
+                            Condition::Header(
+                                HeaderName::from_static("x-canary"),
+                                ValueMatcher::Present,
+                            ),
+                            Condition::Method(vec![Method::GET]),
+                        ]),
+                    ]),
+                    "canary",
+                ),
+                route(&[], "/api", "reads"),
+            ],
+            "web",
+        );
+        let pool = |request: Request<()>| {
+            table
+                .pool_for(&RouteRequest::new(&request))
+                .unwrap()
+                .to_string()
+        };
+        let with_header = |method, uri, name, value| {
+            let mut request = request(None, method, uri);
+            request.headers_mut().append(name, value);
+            request
+        };
+
+        assert_eq!(pool(request(None, Method::GET, "/api/users")), "reads");
+        assert_eq!(pool(request(None, Method::POST, "/api/users")), "writes");
+        assert_eq!(pool(request(None, Method::POST, "/other")), "web");
+        assert_eq!(pool(request(None, Method::GET, "/api?beta=1")), "canary");
+        assert_eq!(pool(request(None, Method::GET, "/api?beta=0")), "reads");
+        // the first matching conditional route wins
+        assert_eq!(pool(request(None, Method::PUT, "/api?beta=1")), "writes");
+        assert_eq!(
+            pool(with_header(
+                Method::GET,
+                "/api",
+                HeaderName::from_static("x-canary"),
+                HeaderValue::from_static("yes")
+            )),
+            "canary"
+        );
+        assert_eq!(
+            pool(with_header(
+                Method::DELETE,
+                "/api",
+                HeaderName::from_static("x-canary"),
+                HeaderValue::from_static("yes")
+            )),
+            "reads"
+        );
+        // conditional routes come before longer unconditional prefixes
+        assert_eq!(
+            pool(with_header(
+                Method::GET,
+                "/api/admin",
+                HeaderName::from_static("x-tenant"),
+                HeaderValue::from_static("acme")
+            )),
+            "acme"
+        );
+        assert_eq!(pool(request(None, Method::POST, "/api/admin")), "writes");
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let strip = PathRewrite {
+            strip_prefix: Some("/api/v1/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(strip.apply("/api/v1/users"), "/users");
+        assert_eq!(strip.apply("/api/v1"), "/");
+        assert_eq!(strip.apply("/api/v10"), "/api/v10");
+
+        let all = PathRewrite {
+            strip_prefix: Some("/api".to_string()),
+            replace: Some((
+                Regex::new(r"^/users/(?P<id>\d+)").unwrap(),
+                "/people/$id".to_string(),
+            )),
+            add_prefix: Some("/v2/".to_string()),
+        };
+        assert_eq!(all.apply("/api/users/42/posts"), "/v2/people/42/posts");
+        assert_eq!(all.apply("/api"), "/v2/");
+
+        let replace = PathRewrite {
+            replace: Some((Regex::new("^/old").unwrap(), String::new())),
+            ..Default::default()
+        };
+        assert_eq!(replace.apply("/old/page"), "/page");
+        assert_eq!(replace.apply("/old"), "/");
+
+        let endpoint = |uri| Uri::from_static(uri);
+        assert_eq!(
+            upstream_url(&endpoint("http://backend:8080"), "/users", None),
+            "http://backend:8080/users"
+        );
+        assert_eq!(
+            upstream_url(&endpoint("http://backend/"), "/users", Some("page=2")),
+            "http://backend/users?page=2"
+        );
+        assert_eq!(
+            upstream_url(&endpoint("https://backend/base/"), "/", None),
+            "https://backend/base/"
+        );
+    }
+
+    #[test]
+    fn test_traffic_split() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        assert!(TrafficSplit::new(vec![("stable".to_string(), 0)]).is_none());
+        let split = TrafficSplit::new(vec![
+            ("stable".to_string(), 90),
+            ("canary".to_string(), 10),
+            ("dark".to_string(), 0),
+        ])
+        .unwrap()
+        .with_header(HeaderName::from_static("x-canary"))
+        .with_cookie("pool");
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let plain = request(None, Method::GET, "/");
+        let mut counts = std::collections::HashMap::new();
+        for _ in 0..10_000 {
+            *counts
+                .entry(split.choose(&RouteRequest::new(&plain), &mut rng))
+                .or_insert(0) += 1;
+        }
+        assert!((8_700..9_300).contains(&counts["stable"]), "{:?}", counts);
+        assert!(!counts.contains_key("dark"));
+
+        let mut forced = request(None, Method::GET, "/");
+        forced
+            .headers_mut()
+            .insert(header::COOKIE, HeaderValue::from_static("a=b; pool=dark"));
+        assert_eq!(split.choose(&RouteRequest::new(&forced), &mut rng), "dark");
+        forced
+            .headers_mut()
+            .insert("x-canary", HeaderValue::from_static("canary"));
+        assert_eq!(
+            split.choose(&RouteRequest::new(&forced), &mut rng),
+            "canary"
+        );
+
+        // only pools in the split can be forced
+        let mut other = request(None, Method::GET, "/");
+        other
+            .headers_mut()
+            .insert("x-canary", HeaderValue::from_static("admin"));
+        assert!(split.set_weights(vec![("stable".to_string(), 0), ("canary".to_string(), 1)]));
+        assert_eq!(split.choose(&RouteRequest::new(&other), &mut rng), "canary");
+        assert!(!split.set_weights(vec![("stable".to_string(), 0)]));
+        assert!(!split.set_weights(vec![
+            ("stable".to_string(), u32::MAX),
+            ("canary".to_string(), 1)
+        ]));
+        assert_eq!(split.weights()[1], ("canary".to_string(), 1));
+        assert!(TrafficSplit::new(vec![
+            ("stable".to_string(), u32::MAX - 1),
+            ("canary".to_string(), 1)
+        ])
+        .is_some());
+    }
+}
@@ -0,0 +1,212 @@
+use opentelemetry::{
+    propagation::{Extractor, Injector, TextMapPropagator},
+    trace::{TraceContextExt, TraceError, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self, Sampler, TracerProvider},
+    Resource,
+};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::utils::constants::{OTEL_ENDPOINT, OTEL_SAMPLE_RATIO, OTEL_SERVICE_NAME};
+
+/// W3C trace context headers passed through to backends.
+const TRACE_HEADERS: [&str; 2] = ["traceparent", "tracestate"];
+
+#[derive(Debug, Clone)]
+pub struct TelemetrySettings {
+    /// OTLP/HTTP collector base URL, e.g. `http://localhost:4318`; export is off when unset.
+    pub endpoint: Option<String>,
+    pub service_name: String,
+    /// Share of new traces to sample. Requests arriving with a sampled `traceparent`
+    /// follow the caller's decision instead.
+    pub sample_ratio: f64,
+}
+
+impl TelemetrySettings {
+    pub fn from_env() -> Self {
+        Self {
+            endpoint: OTEL_ENDPOINT.clone(),
+            service_name: OTEL_SERVICE_NAME.clone(),
+            sample_ratio: *OTEL_SAMPLE_RATIO,
+        }
+    }
+}
+
+/// Install the global `tracing` subscriber, exporting spans over OTLP when configured.
+///
+/// Returns the tracer provider so it can be flushed on shutdown.
+pub fn init_tracing(settings: &TelemetrySettings) -> Option<TracerProvider> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let provider = settings.endpoint.as_deref().and_then(|endpoint| {
+        match tracer_provider(settings, endpoint) {
+            Ok(provider) => Some(provider),
+            Err(e) => {
+                eprintln!("failed to set up trace export to {}: {}", endpoint, e);
+                None
+            }
+        }
+    });
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("roundest-robin-router"))
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(otel_layer)
+        .init();
+
+    provider
+}
+
+pub fn tracer_provider(
+    settings: &TelemetrySettings,
+    endpoint: &str,
+) -> Result<TracerProvider, TraceError> {
+    let exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(endpoint)
+        .build_span_exporter()?;
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_config(
+            trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    settings.sample_ratio,
+                ))))
+                .with_resource(Resource::new([KeyValue::new(
+                    "service.name",
+                    settings.service_name.clone(),
+                )])),
+        )
+        .build())
+}
+
+/// Reads trace context from an incoming request.
+pub struct HeaderExtractor<'a>(pub &'a axum::http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Writes trace context onto an upstream request. reqwest is on a different `http`
+/// version from axum, hence a second header type.
+pub struct ReqwestInjector<'a>(pub &'a mut reqwest::header::HeaderMap);
+
+impl Injector for ReqwestInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+pub fn extract_context(headers: &axum::http::HeaderMap) -> Context {
+    TraceContextPropagator::new().extract(&HeaderExtractor(headers))
+}
+
+/// Propagate `span` to the backend, or pass the caller's trace headers through untouched
+/// when spans are not being recorded.
+pub fn inject_context(
+    span: &tracing::Span,
+    incoming: &axum::http::HeaderMap,
+    outgoing: &mut reqwest::header::HeaderMap,
+) {
+    let context = span.context();
+    if context.span().span_context().is_valid() {
+        TraceContextPropagator::new().inject_context(&context, &mut ReqwestInjector(outgoing));
+        return;
+    }
+
+    for name in TRACE_HEADERS {
+        if let Some(value) = incoming
+            .get(name)
+            .and_then(|value| reqwest::header::HeaderValue::from_bytes(value.as_bytes()).ok())
+        {
+            outgoing.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{extract::State, http::HeaderMap, routing::post, Router};
+    use opentelemetry::trace::{Span, SpanContext, TraceId, Tracer};
+
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_extract_and_pass_through() {
+        let mut incoming = HeaderMap::new();
+        incoming.insert("traceparent", TRACEPARENT.parse().unwrap());
+        incoming.insert("tracestate", "vendor=value".parse().unwrap());
+
+        let context = extract_context(&incoming);
+        let span = context.span();
+        let span_context: &SpanContext = span.span_context();
+        assert_eq!(
+            span_context.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+        );
+        assert!(span_context.is_sampled());
+
+        // no subscriber records spans here, so the caller's headers go through as-is
+        let mut outgoing = reqwest::header::HeaderMap::new();
+        inject_context(&tracing::Span::none(), &incoming, &mut outgoing);
+        assert_eq!(outgoing["traceparent"], TRACEPARENT);
+        assert_eq!(outgoing["tracestate"], "vendor=value");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spans_are_exported_to_collector() {
+        let received = Arc::new(AtomicUsize::new(0));
+        let collector = Router::new()
+            .route(
+                "/v1/traces",
+                post(|State(received): State<Arc<AtomicUsize>>| async move {
+                    received.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, collector).await });
+
+        let settings = TelemetrySettings {
+            endpoint: Some(endpoint.clone()),
+            service_name: "router-test".to_string(),
+            sample_ratio: 1.0,
+        };
+        let provider = tracer_provider(&settings, &endpoint).unwrap();
+        provider.tracer("test").start("request").end();
+
+        tokio::task::spawn_blocking(move || provider.force_flush())
+            .await
+            .unwrap();
+        assert_eq!(received.load(Ordering::SeqCst), 1);
+    }
+}
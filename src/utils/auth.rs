@@ -0,0 +1,158 @@
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use super::constants::{JWT_AUDIENCE, JWT_ISSUER, JWT_SECRET};
+use crate::domain::RouterError;
+
+pub mod scopes {
+    pub const STATS_READ: &str = "stats:read";
+    pub const ENDPOINTS_WRITE: &str = "endpoints:write";
+    pub const ROUTES_WRITE: &str = "routes:write";
+}
+
+/// Claims carried by admin tokens. `scope` is a space separated list, as in OAuth 2.0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub iss: String,
+    pub aud: String,
+    pub exp: usize,
+    #[serde(default)]
+    pub scope: String,
+}
+
+impl Claims {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split_whitespace().any(|s| s == scope)
+    }
+
+    pub fn require_scope(&self, scope: &str) -> Result<(), RouterError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(RouterError::InsufficientScope)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthSettings {
+    secret: String,
+    issuer: String,
+    audience: String,
+}
+
+impl AuthSettings {
+    pub fn new(secret: &str, issuer: &str, audience: &str) -> Self {
+        Self {
+            secret: secret.to_string(),
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(&JWT_SECRET, &JWT_ISSUER, &JWT_AUDIENCE)
+    }
+
+    /// Check signature, issuer, audience and expiry and return the token's claims.
+    pub fn validate_token(&self, token: &str) -> Result<Claims, RouterError> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.secret.as_bytes()),
+            &validation,
+        )
+        .map(|data| data.claims)
+        .map_err(|_| RouterError::InvalidToken)
+    }
+
+    /// Mint a token for `subject` holding `scopes`, valid for `ttl_seconds`.
+    pub fn generate_token(
+        &self,
+        subject: &str,
+        scopes: &[&str],
+        ttl_seconds: i64,
+    ) -> Result<String, RouterError> {
+        let exp = chrono::Utc::now()
+            .checked_add_signed(chrono::Duration::seconds(ttl_seconds))
+            .ok_or(RouterError::UnexpectedError)?
+            .timestamp();
+
+        let claims = Claims {
+            sub: subject.to_string(),
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            exp: exp.try_into().map_err(|_| RouterError::UnexpectedError)?,
+            scope: scopes.join(" "),
+        };
+
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.secret.as_bytes()),
+        )
+        .map_err(|_| RouterError::UnexpectedError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> AuthSettings {
+        AuthSettings::new("secret", "roundest-robin", "roundest-robin-admin")
+    }
+
+    #[test]
+    fn test_validate_token_with_scopes() {
+        let settings = settings();
+        let token = settings
+            .generate_token("ops", &[scopes::STATS_READ], 600)
+            .unwrap();
+
+        let claims = settings.validate_token(&token).unwrap();
+        assert_eq!(claims.sub, "ops");
+        assert!(claims.require_scope(scopes::STATS_READ).is_ok());
+        assert!(matches!(
+            claims.require_scope(scopes::ENDPOINTS_WRITE),
+            Err(RouterError::InsufficientScope)
+        ));
+    }
+
+    #[test]
+    fn test_validate_token_wrong_audience() {
+        let token = AuthSettings::new("secret", "roundest-robin", "someone-else")
+            .generate_token("ops", &[scopes::STATS_READ], 600)
+            .unwrap();
+
+        assert!(settings().validate_token(&token).is_err());
+    }
+
+    #[test]
+    fn test_validate_token_wrong_issuer_or_secret() {
+        let wrong_issuer = AuthSettings::new("secret", "intruder", "roundest-robin-admin")
+            .generate_token("ops", &[scopes::STATS_READ], 600)
+            .unwrap();
+        let wrong_secret = AuthSettings::new("other", "roundest-robin", "roundest-robin-admin")
+            .generate_token("ops", &[scopes::STATS_READ], 600)
+            .unwrap();
+
+        assert!(settings().validate_token(&wrong_issuer).is_err());
+        assert!(settings().validate_token(&wrong_secret).is_err());
+    }
+
+    #[test]
+    fn test_validate_token_expired() {
+        // well past the default 60 second leeway
+        let token = settings()
+            .generate_token("ops", &[scopes::STATS_READ], -600)
+            .unwrap();
+
+        assert!(settings().validate_token(&token).is_err());
+    }
+}
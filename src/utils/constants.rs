@@ -4,6 +4,153 @@ use std::env as std_env;
 
 lazy_static! {
     pub static ref JWT_SECRET: String = set_token();
+    pub static ref JWT_ISSUER: String = set_with_default(env::JWT_ISSUER_ENV_VAR, "roundest-robin");
+    pub static ref JWT_AUDIENCE: String =
+        set_with_default(env::JWT_AUDIENCE_ENV_VAR, "roundest-robin-admin");
+    pub static ref ADMIN_ADDRESS: Option<String> = set_optional(env::ADMIN_ADDRESS_ENV_VAR);
+    pub static ref DOCKER_DISCOVERY_POOL: Option<String> =
+        set_optional(env::DOCKER_DISCOVERY_POOL_ENV_VAR);
+    pub static ref DOCKER_DISCOVERY_INTERVAL_SECS: u64 =
+        set_interval_secs(env::DOCKER_DISCOVERY_INTERVAL_SECS_ENV_VAR, "30");
+    pub static ref DNS_DISCOVERY_NAME: Option<String> =
+        set_optional(env::DNS_DISCOVERY_NAME_ENV_VAR);
+    pub static ref DNS_DISCOVERY_PORT: Option<u16> = set_optional(env::DNS_DISCOVERY_PORT_ENV_VAR)
+        .map(|port| port
+            .parse()
+            .expect("DNS_DISCOVERY_PORT must be a port number."));
+    pub static ref DNS_DISCOVERY_SCHEME: String =
+        set_with_default(env::DNS_DISCOVERY_SCHEME_ENV_VAR, "http");
+    pub static ref DNS_DISCOVERY_INTERVAL_SECS: u64 =
+        set_interval_secs(env::DNS_DISCOVERY_INTERVAL_SECS_ENV_VAR, "30");
+    pub static ref DNS_DISCOVERY_POOL: Option<String> =
+        set_optional(env::DNS_DISCOVERY_POOL_ENV_VAR);
+    pub static ref DOCKER_ENABLED: bool = set_with_default(env::DOCKER_ENABLED_ENV_VAR, "true")
+        .parse()
+        .expect("DOCKER_ENABLED must be true or false.");
+    // ROUTER_DOCKER_HOST lets the router use a different daemon from the local docker CLI
+    pub static ref DOCKER_HOST: Option<String> = set_optional(env::ROUTER_DOCKER_HOST_ENV_VAR)
+        .or_else(|| set_optional(env::DOCKER_HOST_ENV_VAR));
+    pub static ref DOCKER_TIMEOUT_SECS: u64 =
+        set_with_default(env::DOCKER_TIMEOUT_SECS_ENV_VAR, "5")
+            .parse()
+            .expect("DOCKER_TIMEOUT_SECS must be a number of seconds.");
+    pub static ref DOCKER_CERT_PATH: Option<String> = set_optional(env::DOCKER_CERT_PATH_ENV_VAR);
+    // the docker CLI treats any non-empty value as enabled
+    pub static ref DOCKER_TLS_VERIFY: bool = set_optional(env::DOCKER_TLS_VERIFY_ENV_VAR)
+        .is_some_and(|value| value != "0" && value != "false");
+    pub static ref FILE_DISCOVERY_PATH: Option<String> =
+        set_optional(env::FILE_DISCOVERY_PATH_ENV_VAR);
+    pub static ref ROUTES_CONFIG: Option<String> = set_optional(env::ROUTES_CONFIG_ENV_VAR);
+    pub static ref FILE_DISCOVERY_INTERVAL_SECS: u64 =
+        set_interval_secs(env::FILE_DISCOVERY_INTERVAL_SECS_ENV_VAR, "2");
+    pub static ref FILE_DISCOVERY_POOL: Option<String> =
+        set_optional(env::FILE_DISCOVERY_POOL_ENV_VAR);
+    pub static ref DOCKER_STATS_INTERVAL_SECS: u64 =
+        set_interval_secs(env::DOCKER_STATS_INTERVAL_SECS_ENV_VAR, "10");
+    pub static ref EVENTS_INTERVAL_SECS: u64 =
+        set_interval_secs(env::EVENTS_INTERVAL_SECS_ENV_VAR, "2");
+    pub static ref BALANCING_STRATEGY: String =
+        set_with_default(env::BALANCING_STRATEGY_ENV_VAR, "round_robin");
+    pub static ref RESOURCE_CPU_SOFT_LIMIT: f64 =
+        set_with_default(env::RESOURCE_CPU_SOFT_LIMIT_ENV_VAR, "70")
+            .parse()
+            .expect("RESOURCE_CPU_SOFT_LIMIT must be a percentage.");
+    pub static ref RESOURCE_CPU_HARD_LIMIT: f64 =
+        set_with_default(env::RESOURCE_CPU_HARD_LIMIT_ENV_VAR, "95")
+            .parse()
+            .expect("RESOURCE_CPU_HARD_LIMIT must be a percentage.");
+    pub static ref RESOURCE_MEMORY_SOFT_LIMIT: f64 =
+        set_with_default(env::RESOURCE_MEMORY_SOFT_LIMIT_ENV_VAR, "80")
+            .parse()
+            .expect("RESOURCE_MEMORY_SOFT_LIMIT must be a percentage.");
+    pub static ref RESOURCE_MEMORY_HARD_LIMIT: f64 =
+        set_with_default(env::RESOURCE_MEMORY_HARD_LIMIT_ENV_VAR, "95")
+            .parse()
+            .expect("RESOURCE_MEMORY_HARD_LIMIT must be a percentage.");
+    pub static ref RESOURCE_STATS_MAX_AGE_SECS: u64 =
+        set_with_default(env::RESOURCE_STATS_MAX_AGE_SECS_ENV_VAR, "10")
+            .parse()
+            .expect("RESOURCE_STATS_MAX_AGE_SECS must be a number of seconds.");
+    pub static ref AUTOSCALE_POOL: Option<String> = set_optional(env::AUTOSCALE_POOL_ENV_VAR);
+    pub static ref AUTOSCALE_IMAGE: Option<String> = set_optional(env::AUTOSCALE_IMAGE_ENV_VAR);
+    pub static ref AUTOSCALE_PORT: u16 =
+        set_with_default(env::AUTOSCALE_PORT_ENV_VAR, "8080")
+            .parse()
+            .expect("AUTOSCALE_PORT must be a port number.");
+    pub static ref AUTOSCALE_NETWORK: Option<String> = set_optional(env::AUTOSCALE_NETWORK_ENV_VAR);
+    pub static ref AUTOSCALE_MIN_REPLICAS: usize =
+        set_with_default(env::AUTOSCALE_MIN_REPLICAS_ENV_VAR, "1")
+            .parse()
+            .expect("AUTOSCALE_MIN_REPLICAS must be a number.");
+    pub static ref AUTOSCALE_MAX_REPLICAS: usize =
+        set_with_default(env::AUTOSCALE_MAX_REPLICAS_ENV_VAR, "5")
+            .parse()
+            .expect("AUTOSCALE_MAX_REPLICAS must be a number.");
+    pub static ref AUTOSCALE_TARGET_CONNECTIONS: f64 =
+        set_with_default(env::AUTOSCALE_TARGET_CONNECTIONS_ENV_VAR, "10")
+            .parse()
+            .expect("AUTOSCALE_TARGET_CONNECTIONS must be a number.");
+    pub static ref AUTOSCALE_TARGET_CPU: f64 =
+        set_with_default(env::AUTOSCALE_TARGET_CPU_ENV_VAR, "70")
+            .parse()
+            .expect("AUTOSCALE_TARGET_CPU must be a percentage.");
+    pub static ref AUTOSCALE_SUSTAIN_SECS: u64 =
+        set_with_default(env::AUTOSCALE_SUSTAIN_SECS_ENV_VAR, "30")
+            .parse()
+            .expect("AUTOSCALE_SUSTAIN_SECS must be a number of seconds.");
+    pub static ref AUTOSCALE_COOLDOWN_SECS: u64 =
+        set_with_default(env::AUTOSCALE_COOLDOWN_SECS_ENV_VAR, "120")
+            .parse()
+            .expect("AUTOSCALE_COOLDOWN_SECS must be a number of seconds.");
+    pub static ref AUTOSCALE_DRAIN_TIMEOUT_SECS: u64 =
+        set_with_default(env::AUTOSCALE_DRAIN_TIMEOUT_SECS_ENV_VAR, "30")
+            .parse()
+            .expect("AUTOSCALE_DRAIN_TIMEOUT_SECS must be a number of seconds.");
+    pub static ref AUTOSCALE_INTERVAL_SECS: u64 =
+        set_interval_secs(env::AUTOSCALE_INTERVAL_SECS_ENV_VAR, "5");
+    pub static ref AUTOSCALE_DRY_RUN: bool = set_with_default(env::AUTOSCALE_DRY_RUN_ENV_VAR, "false")
+        .parse()
+        .expect("AUTOSCALE_DRY_RUN must be true or false.");
+    pub static ref PROXY_MAX_RETRIES: usize = set_with_default(env::PROXY_MAX_RETRIES_ENV_VAR, "0")
+        .parse()
+        .expect("PROXY_MAX_RETRIES must be a number.");
+    pub static ref PROXY_MAX_BODY_BYTES: usize =
+        set_with_default(env::PROXY_MAX_BODY_BYTES_ENV_VAR, "10485760")
+            .parse()
+            .expect("PROXY_MAX_BODY_BYTES must be a number of bytes.");
+    pub static ref PROXY_TIMEOUT_MS: Option<u64> = set_optional(env::PROXY_TIMEOUT_MS_ENV_VAR)
+        .map(|value| value.parse().expect("PROXY_TIMEOUT_MS must be a number of milliseconds."));
+    pub static ref MIRROR_MAX_IN_FLIGHT: usize =
+        set_with_default(env::MIRROR_MAX_IN_FLIGHT_ENV_VAR, "100")
+            .parse()
+            .expect("MIRROR_MAX_IN_FLIGHT must be a number.");
+    pub static ref ERROR_PAGES_DIR: Option<String> = set_optional(env::ERROR_PAGES_DIR_ENV_VAR);
+    pub static ref MAINTENANCE_MODE: bool = set_with_default(env::MAINTENANCE_MODE_ENV_VAR, "false")
+        .parse()
+        .expect("MAINTENANCE_MODE must be true or false.");
+    pub static ref ACCESS_LOG: String = set_with_default(env::ACCESS_LOG_ENV_VAR, "stdout");
+    pub static ref ACCESS_LOG_FORMAT: String =
+        set_with_default(env::ACCESS_LOG_FORMAT_ENV_VAR, "combined");
+    pub static ref ACCESS_LOG_MAX_BYTES: u64 =
+        set_with_default(env::ACCESS_LOG_MAX_BYTES_ENV_VAR, "10485760")
+            .parse()
+            .expect("ACCESS_LOG_MAX_BYTES must be a number of bytes.");
+    pub static ref ACCESS_LOG_MAX_FILES: usize =
+        set_with_default(env::ACCESS_LOG_MAX_FILES_ENV_VAR, "5")
+            .parse()
+            .expect("ACCESS_LOG_MAX_FILES must be a number.");
+    pub static ref ACCESS_LOG_SAMPLE_RATE: f64 =
+        set_with_default(env::ACCESS_LOG_SAMPLE_RATE_ENV_VAR, "1")
+            .parse()
+            .expect("ACCESS_LOG_SAMPLE_RATE must be a number between 0 and 1.");
+    pub static ref ACCESS_LOG_EXCLUDE_PATHS: String =
+        set_with_default(env::ACCESS_LOG_EXCLUDE_PATHS_ENV_VAR, "");
+    pub static ref OTEL_ENDPOINT: Option<String> = set_optional(env::OTEL_ENDPOINT_ENV_VAR);
+    pub static ref OTEL_SERVICE_NAME: String =
+        set_with_default(env::OTEL_SERVICE_NAME_ENV_VAR, "roundest-robin");
+    pub static ref OTEL_SAMPLE_RATIO: f64 = set_with_default(env::OTEL_SAMPLE_RATIO_ENV_VAR, "1")
+        .parse()
+        .expect("OTEL_TRACES_SAMPLER_ARG must be a number between 0 and 1.");
 }
 
 fn set_token() -> String {
@@ -15,11 +162,86 @@ fn set_token() -> String {
     secret
 }
 
+fn set_optional(name: &str) -> Option<String> {
+    dotenv().ok();
+    std_env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn set_with_default(name: &str, default: &str) -> String {
+    set_optional(name).unwrap_or_else(|| default.to_string())
+}
+
+/// Seconds between runs of a background task; 0 would make its ticker panic.
+fn set_interval_secs(name: &str, default: &str) -> u64 {
+    match set_with_default(name, default).parse() {
+        Ok(0) | Err(_) => panic!("{} must be a whole number of seconds above 0.", name),
+        Ok(secs) => secs,
+    }
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
+    pub const ADMIN_ADDRESS_ENV_VAR: &str = "ADMIN_ADDRESS";
+    pub const DOCKER_DISCOVERY_POOL_ENV_VAR: &str = "DOCKER_DISCOVERY_POOL";
+    pub const DOCKER_DISCOVERY_INTERVAL_SECS_ENV_VAR: &str = "DOCKER_DISCOVERY_INTERVAL_SECS";
+    pub const DNS_DISCOVERY_NAME_ENV_VAR: &str = "DNS_DISCOVERY_NAME";
+    pub const DNS_DISCOVERY_PORT_ENV_VAR: &str = "DNS_DISCOVERY_PORT";
+    pub const DNS_DISCOVERY_SCHEME_ENV_VAR: &str = "DNS_DISCOVERY_SCHEME";
+    pub const DNS_DISCOVERY_INTERVAL_SECS_ENV_VAR: &str = "DNS_DISCOVERY_INTERVAL_SECS";
+    pub const DNS_DISCOVERY_POOL_ENV_VAR: &str = "DNS_DISCOVERY_POOL";
+    pub const DOCKER_ENABLED_ENV_VAR: &str = "DOCKER_ENABLED";
+    pub const ROUTER_DOCKER_HOST_ENV_VAR: &str = "ROUTER_DOCKER_HOST";
+    pub const DOCKER_HOST_ENV_VAR: &str = "DOCKER_HOST";
+    pub const DOCKER_TIMEOUT_SECS_ENV_VAR: &str = "DOCKER_TIMEOUT_SECS";
+    pub const DOCKER_CERT_PATH_ENV_VAR: &str = "DOCKER_CERT_PATH";
+    pub const DOCKER_TLS_VERIFY_ENV_VAR: &str = "DOCKER_TLS_VERIFY";
+    pub const FILE_DISCOVERY_PATH_ENV_VAR: &str = "FILE_DISCOVERY_PATH";
+    pub const ROUTES_CONFIG_ENV_VAR: &str = "ROUTES_CONFIG";
+    pub const FILE_DISCOVERY_INTERVAL_SECS_ENV_VAR: &str = "FILE_DISCOVERY_INTERVAL_SECS";
+    pub const FILE_DISCOVERY_POOL_ENV_VAR: &str = "FILE_DISCOVERY_POOL";
+    pub const DOCKER_STATS_INTERVAL_SECS_ENV_VAR: &str = "DOCKER_STATS_INTERVAL_SECS";
+    pub const EVENTS_INTERVAL_SECS_ENV_VAR: &str = "EVENTS_INTERVAL_SECS";
+    pub const BALANCING_STRATEGY_ENV_VAR: &str = "BALANCING_STRATEGY";
+    pub const RESOURCE_CPU_SOFT_LIMIT_ENV_VAR: &str = "RESOURCE_CPU_SOFT_LIMIT";
+    pub const RESOURCE_CPU_HARD_LIMIT_ENV_VAR: &str = "RESOURCE_CPU_HARD_LIMIT";
+    pub const RESOURCE_MEMORY_SOFT_LIMIT_ENV_VAR: &str = "RESOURCE_MEMORY_SOFT_LIMIT";
+    pub const RESOURCE_MEMORY_HARD_LIMIT_ENV_VAR: &str = "RESOURCE_MEMORY_HARD_LIMIT";
+    pub const RESOURCE_STATS_MAX_AGE_SECS_ENV_VAR: &str = "RESOURCE_STATS_MAX_AGE_SECS";
+    pub const AUTOSCALE_POOL_ENV_VAR: &str = "AUTOSCALE_POOL";
+    pub const AUTOSCALE_IMAGE_ENV_VAR: &str = "AUTOSCALE_IMAGE";
+    pub const AUTOSCALE_PORT_ENV_VAR: &str = "AUTOSCALE_PORT";
+    pub const AUTOSCALE_NETWORK_ENV_VAR: &str = "AUTOSCALE_NETWORK";
+    pub const AUTOSCALE_MIN_REPLICAS_ENV_VAR: &str = "AUTOSCALE_MIN_REPLICAS";
+    pub const AUTOSCALE_MAX_REPLICAS_ENV_VAR: &str = "AUTOSCALE_MAX_REPLICAS";
+    pub const AUTOSCALE_TARGET_CONNECTIONS_ENV_VAR: &str = "AUTOSCALE_TARGET_CONNECTIONS";
+    pub const AUTOSCALE_TARGET_CPU_ENV_VAR: &str = "AUTOSCALE_TARGET_CPU";
+    pub const AUTOSCALE_SUSTAIN_SECS_ENV_VAR: &str = "AUTOSCALE_SUSTAIN_SECS";
+    pub const AUTOSCALE_COOLDOWN_SECS_ENV_VAR: &str = "AUTOSCALE_COOLDOWN_SECS";
+    pub const AUTOSCALE_DRAIN_TIMEOUT_SECS_ENV_VAR: &str = "AUTOSCALE_DRAIN_TIMEOUT_SECS";
+    pub const AUTOSCALE_INTERVAL_SECS_ENV_VAR: &str = "AUTOSCALE_INTERVAL_SECS";
+    pub const AUTOSCALE_DRY_RUN_ENV_VAR: &str = "AUTOSCALE_DRY_RUN";
+    pub const PROXY_MAX_RETRIES_ENV_VAR: &str = "PROXY_MAX_RETRIES";
+    pub const PROXY_MAX_BODY_BYTES_ENV_VAR: &str = "PROXY_MAX_BODY_BYTES";
+    pub const PROXY_TIMEOUT_MS_ENV_VAR: &str = "PROXY_TIMEOUT_MS";
+    pub const MIRROR_MAX_IN_FLIGHT_ENV_VAR: &str = "MIRROR_MAX_IN_FLIGHT";
+    pub const ERROR_PAGES_DIR_ENV_VAR: &str = "ERROR_PAGES_DIR";
+    pub const MAINTENANCE_MODE_ENV_VAR: &str = "MAINTENANCE_MODE";
+    pub const ACCESS_LOG_ENV_VAR: &str = "ACCESS_LOG";
+    pub const ACCESS_LOG_FORMAT_ENV_VAR: &str = "ACCESS_LOG_FORMAT";
+    pub const ACCESS_LOG_MAX_BYTES_ENV_VAR: &str = "ACCESS_LOG_MAX_BYTES";
+    pub const ACCESS_LOG_MAX_FILES_ENV_VAR: &str = "ACCESS_LOG_MAX_FILES";
+    pub const ACCESS_LOG_SAMPLE_RATE_ENV_VAR: &str = "ACCESS_LOG_SAMPLE_RATE";
+    pub const ACCESS_LOG_EXCLUDE_PATHS_ENV_VAR: &str = "ACCESS_LOG_EXCLUDE_PATHS";
+    // the standard OpenTelemetry names, so existing deployment config carries over
+    pub const OTEL_ENDPOINT_ENV_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+    pub const OTEL_SERVICE_NAME_ENV_VAR: &str = "OTEL_SERVICE_NAME";
+    pub const OTEL_SAMPLE_RATIO_ENV_VAR: &str = "OTEL_TRACES_SAMPLER_ARG";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";